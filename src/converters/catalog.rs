@@ -0,0 +1,136 @@
+use super::header::Header;
+
+/// 消息的唯一键: (msgctxt, msgid)
+pub type MessageKey = (Option<String>, String);
+
+/// 翻译条目，所有转换器共用的数据模型
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    pub msgctxt: Option<String>,        // 消息上下文
+    pub msgid: String,                  // 原文
+    pub msgid_plural: Option<String>,   // 复数形式原文
+    pub msgstr: Vec<String>,            // 译文，复数条目按 msgstr[n] 顺序存放
+    pub comments: Vec<String>,          // 译者注释 (# )
+    pub extracted_comments: Vec<String>, // 提取的注释 (#.)
+    pub references: Vec<String>,        // 源码位置引用 (#:)
+    pub flags: Vec<String>,             // 标记 (#, fuzzy, c-format 等)
+    pub previous_msgctxt: Option<String>,     // 之前的上下文 (#| msgctxt)
+    pub previous_msgid: Option<String>,       // 之前的原文 (#| msgid)
+    pub previous_msgid_plural: Option<String>, // 之前的复数原文 (#| msgid_plural)
+    pub obsolete: bool,                 // 过时条目 (#~)
+    pub source_file: String,            // 来源文件
+    pub line_number: usize,             // 在来源文件中的行号
+}
+
+impl Entry {
+    /// 创建一个普通(非复数)条目
+    pub fn new(msgctxt: Option<String>, msgid: String, msgstr: String) -> Self {
+        Self {
+            msgctxt,
+            msgid,
+            msgstr: vec![msgstr],
+            ..Default::default()
+        }
+    }
+
+    /// 条目的键
+    pub fn key(&self) -> MessageKey {
+        (self.msgctxt.clone(), self.msgid.clone())
+    }
+
+    /// 单数译文 (msgstr 或 msgstr[0])
+    pub fn msgstr(&self) -> &str {
+        self.msgstr.first().map(|s| s.as_str()).unwrap_or("")
+    }
+
+    /// 是否带有指定标记
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    /// 是否为模糊翻译
+    pub fn is_fuzzy(&self) -> bool {
+        self.has_flag("fuzzy")
+    }
+
+    /// 是否已翻译 (至少有一个非空译文)
+    pub fn is_translated(&self) -> bool {
+        self.msgstr.iter().any(|s| !s.is_empty())
+    }
+
+    /// MO文件中使用的原文键: msgctxt 与 msgid 以 EOT(0x04) 连接，
+    /// 复数条目再以 NUL 连接 msgid_plural
+    pub fn mo_msgid(&self) -> String {
+        let mut key = match &self.msgctxt {
+            Some(ctx) => format!("{}\u{4}{}", ctx, self.msgid),
+            None => self.msgid.clone(),
+        };
+        if let Some(plural) = &self.msgid_plural {
+            key.push('\0');
+            key.push_str(plural);
+        }
+        key
+    }
+
+    /// MO文件中使用的译文: 复数译文以 NUL 连接
+    pub fn mo_msgstr(&self) -> String {
+        self.msgstr.join("\0")
+    }
+}
+
+/// 翻译目录: 头部信息加上按读取顺序排列的条目
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    pub header: Header,               // 头部条目(msgid "")的译文
+    pub header_comments: Vec<String>, // 头部条目的译者注释
+    pub entries: Vec<Entry>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 条目数量(不含头部)
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 从头部的 `Plural-Forms` 中读取复数形式的数量，缺省为2
+    pub fn nplurals(&self) -> usize {
+        self.header.nplurals()
+    }
+}
+
+/// 在头部文本中查找字段的值
+pub fn find_header_field<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(name) {
+            Some(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+/// 从头部文本的 `Plural-Forms` 中读取复数形式的数量，缺省为2
+pub fn parse_nplurals(header: &str) -> usize {
+    nplurals_in(find_header_field(header, "Plural-Forms"))
+}
+
+/// 从 `Plural-Forms` 字段的值中读取复数形式的数量，缺省为2
+pub fn nplurals_in(plural_forms: Option<&str>) -> usize {
+    plural_forms
+        .and_then(|forms| {
+            forms.split(';').find_map(|part| {
+                let (key, value) = part.split_once('=')?;
+                if key.trim() == "nplurals" { value.trim().parse().ok() } else { None }
+            })
+        })
+        .unwrap_or(2)
+}
@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+
+use encoding_rs::{Encoding, UTF_8};
+
+use super::error::{ConvertError, Location};
+
+/// 在PO文件开头查找头部 charset 时最多检查的字节数
+pub const HEADER_PROBE_LEN: usize = 64 * 1024;
+
+/// 从头部文本中读取 `Content-Type` 的 charset
+///
+/// 同时适用于已解析的头部和PO源文本(其中换行为字面量 `\n`)
+pub fn header_charset(header: &str) -> Option<&str> {
+    let start = header.find("charset=")? + "charset=".len();
+    let rest = &header[start..];
+    let end = rest
+        .find(|c: char| c == ';' || c == '\\' || c == '"' || c.is_whitespace())
+        .unwrap_or(rest.len());
+    let charset = rest[..end].trim();
+    if charset.is_empty() { None } else { Some(charset) }
+}
+
+/// 根据 charset 名称获取编码，未声明或为模板占位符 `CHARSET` 时使用UTF-8
+pub fn encoding_for_charset(charset: Option<&str>) -> Result<&'static Encoding, ConvertError> {
+    match charset {
+        None => Ok(UTF_8),
+        Some(name) if name.eq_ignore_ascii_case("CHARSET") => Ok(UTF_8),
+        Some(name) => Encoding::for_label(name.as_bytes())
+            .ok_or_else(|| ConvertError::UnsupportedCharset(name.to_string())),
+    }
+}
+
+/// 按指定编码解码字节，遇到无效字符时返回错误；UTF-8内容直接借用，不复制
+///
+/// UTF-8的错误带有字节偏移，其他编码的错误不带位置
+pub fn decode<'a>(bytes: &'a [u8], encoding: &'static Encoding) -> Result<Cow<'a, str>, ConvertError> {
+    if encoding == UTF_8 {
+        return std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|e| ConvertError::Encoding { encoding: UTF_8.name(), location: Location::Offset(e.valid_up_to()) });
+    }
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .ok_or(ConvertError::Encoding { encoding: encoding.name(), location: Location::Unknown })
+}
+
+/// 根据PO文件开头的内容判断编码，返回编码和BOM的长度
+///
+/// 优先使用BOM，其次使用头部声明的 charset，都没有时按UTF-8处理
+pub fn probe_po_encoding(bytes: &[u8]) -> Result<(&'static Encoding, usize), ConvertError> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return Ok((encoding, bom_len));
+    }
+
+    let probe = String::from_utf8_lossy(&bytes[..bytes.len().min(HEADER_PROBE_LEN)]);
+    Ok((encoding_for_charset(header_charset(&probe))?, 0))
+}
+
+/// 将头部中的 charset 改写为UTF-8，没有声明 charset 时保持不变
+pub fn with_utf8_charset(header: &str) -> String {
+    match header_charset(header) {
+        Some(charset) if charset != "UTF-8" => {
+            let start = header.find("charset=").unwrap_or(0) + "charset=".len();
+            format!("{}UTF-8{}", &header[..start], &header[start + charset.len()..])
+        }
+        _ => header.to_string(),
+    }
+}
@@ -1,8 +1,13 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader};
 use std::path::Path;
 use chrono::Local;
 
+use super::catalog::{Catalog, Entry};
+use super::error::{ConvertError, IoOp};
+use super::header::{self, Header};
+use super::po_writer::{self, WriteOptions};
+
 pub struct CsvConverter;
 
 impl CsvConverter {
@@ -16,24 +21,42 @@ impl CsvConverter {
     /// # Returns
     /// 
     /// 成功返回Ok(()), 失败返回带错误信息的Err
-    pub fn convert_csv_to_po(input: &Path, output: &Path) -> Result<(), String> {
+    pub fn convert_csv_to_po(input: &Path, output: &Path) -> Result<(), ConvertError> {
+        // 保持CSV中的行顺序
+        Self::convert_csv_to_po_with(input, output, &WriteOptions::input_order())
+    }
+    
+    /// 按指定写出选项将CSV文件转换为PO文件
+    ///
+    /// 稳定输出时CSV没有变化就不会改写输出文件，生成时间不会更新
+    pub fn convert_csv_to_po_with(input: &Path, output: &Path, options: &WriteOptions) -> Result<(), ConvertError> {
+        let catalog = Self::read_csv_file(input)?;
+        po_writer::write_po_file(&catalog, output, options)
+    }
+    
+    /// 读取CSV文件为翻译目录
+    /// 
+    /// 第一列为译文，第二列为原文；看起来像表头的首行会被跳过
+    pub fn read_csv_file(input: &Path) -> Result<Catalog, ConvertError> {
         // 打开CSV文件
-        let file = File::open(input).map_err(|e| format!("无法打开CSV文件: {}", e))?;
+        let file = File::open(input).map_err(|e| ConvertError::io(IoOp::Open, input, e))?;
         let reader = BufReader::new(file);
         
-        // 创建输出PO文件
-        let mut output_file = File::create(output).map_err(|e| format!("无法创建PO文件: {}", e))?;
+        let source_file = input.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
         
         // 生成PO文件头
-        write_po_header(&mut output_file)?;
+        let mut catalog = Catalog::new();
+        catalog.header = po_header();
         
         // 读取并处理每一行
         let mut is_first_line = true;
         let mut has_header = false;
-        let mut entries_count = 0;
         
-        for line in reader.lines() {
-            let mut line = line.map_err(|e| format!("读取CSV文件时出错: {}", e))?;
+        for (index, line) in reader.lines().enumerate() {
+            let mut line = line.map_err(|e| ConvertError::io(IoOp::Read, input, e))?;
             
             // 处理BOM标记（UTF-8 BOM）
             if is_first_line && line.starts_with('\u{feff}') {
@@ -48,7 +71,8 @@ impl CsvConverter {
             }
             
             // 解析CSV行
-            let entries = parse_csv_line(&line)?;
+            let entries = parse_csv_line(&line)
+                .map_err(|message| ConvertError::Validation { line: Some(index + 1), message })?;
             
             // 必须有源文本和目标文本
             if entries.len() < 2 {
@@ -77,22 +101,18 @@ impl CsvConverter {
                 continue;
             }
             
-            // 写入PO条目
-            writeln!(output_file, "msgid {}", escape_po_string(msgid))
-                .map_err(|e| format!("写入PO文件时出错: {}", e))?;
-            writeln!(output_file, "msgstr {}", escape_po_string(msgstr))
-                .map_err(|e| format!("写入PO文件时出错: {}", e))?;
-            writeln!(output_file).map_err(|e| format!("写入PO文件时出错: {}", e))?;
-            
-            entries_count += 1;
+            let mut entry = Entry::new(None, msgid.clone(), msgstr.clone());
+            entry.source_file = source_file.clone();
+            entry.line_number = index + 1;
+            catalog.entries.push(entry);
         }
         
         // 如果没有有效条目，返回错误
-        if entries_count == 0 {
-            return Err("CSV文件中未找到有效翻译条目".to_string());
+        if catalog.is_empty() {
+            return Err(ConvertError::Validation { line: None, message: "CSV文件中未找到有效翻译条目".to_string() });
         }
         
-        Ok(())
+        Ok(catalog)
     }
 }
 
@@ -158,34 +178,23 @@ fn parse_csv_line(line: &str) -> Result<Vec<String>, String> {
     Ok(result)
 }
 
-/// 将字符串转义为PO格式
-fn escape_po_string(s: &str) -> String {
-    let escaped = s.replace('\\', "\\\\")
-                   .replace('\"', "\\\"")
-                   .replace('\n', "\\n")
-                   .replace('\r', "\\r")
-                   .replace('\t', "\\t");
-    
-    format!("\"{}\"", escaped)
-}
-
-/// 写入PO文件头
-fn write_po_header(file: &mut File) -> Result<(), String> {
+/// 生成PO文件头
+///
+/// CSV中没有语言信息，不写 `Language` 和 `Plural-Forms`，合并时以主文件的头部为准
+fn po_header() -> Header {
     let now = Local::now();
     let date_str = now.format("%Y-%m-%d %H:%M%z").to_string();
     
     // 编写PO文件头
-    writeln!(file, "msgid \"\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "msgstr \"\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"Project-Id-Version: BLMM Converted CSV\\n\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"POT-Creation-Date: {}\\n\"", date_str).map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"PO-Revision-Date: {}\\n\"", date_str).map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"Language: zh_CN\\n\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"MIME-Version: 1.0\\n\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"Content-Type: text/plain; charset=UTF-8\\n\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"Content-Transfer-Encoding: 8bit\\n\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file, "\"Converted-From-CSV: true\\n\"").map_err(|e| format!("写入PO文件头时出错: {}", e))?;
-    writeln!(file).map_err(|e| format!("写入PO文件头时出错: {}", e))?;
+    let mut header = Header::new();
+    header.set(header::PROJECT_ID_VERSION, "BLMM Converted CSV");
+    header.set(header::POT_CREATION_DATE, &date_str);
+    header.set(header::PO_REVISION_DATE, &date_str);
+    header.set(header::MIME_VERSION, "1.0");
+    header.set(header::CONTENT_TYPE, "text/plain; charset=UTF-8");
+    header.set(header::CONTENT_TRANSFER_ENCODING, "8bit");
+    header.set(header::GENERATOR, header::GENERATOR_NAME);
+    header.set("Converted-From-CSV", "true");
     
-    Ok(())
-} 
\ No newline at end of file
+    header
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::po_parser::Diagnostic;
+
+/// 文件操作类型，用于区分I/O错误发生在哪一步
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoOp {
+    Open,
+    Create,
+    Read,
+    Write,
+    Rename,
+}
+
+/// 错误在输入数据中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Unknown,
+    Offset(usize),                       // 字节偏移
+    Entry(usize),                        // MO文件中的第几个字符串，从0开始
+}
+
+/// 转换层的错误
+///
+/// 各变体带有结构化的信息，界面和命令行可以据此区分具体的失败原因并自行组织提示；
+/// `Display` 给出默认的中文描述
+#[derive(Debug)]
+pub enum ConvertError {
+    /// 文件读写失败
+    Io { op: IoOp, path: Option<PathBuf>, source: io::Error },
+    /// MO文件魔数不正确
+    BadMagic { found: u32 },
+    /// 不支持的MO文件版本
+    UnsupportedRevision { major: u32, minor: u32 },
+    /// 数据被截断: 需要读取 `offset` 开始的 `len` 字节，但数据只有 `available` 字节
+    Truncated { offset: usize, len: usize, available: usize },
+    /// 文件结构错误
+    Format { location: Location, message: String },
+    /// PO语法错误
+    Parse(Diagnostic),
+    /// 内容不符合声明的编码
+    Encoding { encoding: &'static str, location: Location },
+    /// 不支持的字符集
+    UnsupportedCharset(String),
+    /// `Plural-Forms` 无效
+    PluralForms(String),
+    /// 输出或输入内容未通过校验
+    Validation { line: Option<usize>, message: String },
+    /// 输入文件未通过校验而被拒绝，带有所有错误级别的问题
+    Rejected(Vec<Diagnostic>),
+    /// 输入文件中有重复的条目，带有每个重复条目的位置
+    Duplicates(Vec<Diagnostic>),
+    /// 操作被取消
+    Cancelled,
+}
+
+impl ConvertError {
+    pub fn io(op: IoOp, path: &Path, source: io::Error) -> Self {
+        ConvertError::Io { op, path: Some(path.to_path_buf()), source }
+    }
+
+    /// 写入输出流失败，不对应具体文件
+    pub fn write(source: io::Error) -> Self {
+        ConvertError::Io { op: IoOp::Write, path: None, source }
+    }
+
+    /// 读取输入流失败，不对应具体文件
+    pub fn read(source: io::Error) -> Self {
+        ConvertError::Io { op: IoOp::Read, path: None, source }
+    }
+
+    pub fn format(location: Location, message: impl Into<String>) -> Self {
+        ConvertError::Format { location, message: message.into() }
+    }
+
+    /// 将编码错误和格式错误的位置改为 `location`，其他错误保持不变
+    pub fn at(self, location: Location) -> Self {
+        match self {
+            ConvertError::Encoding { encoding, .. } => ConvertError::Encoding { encoding, location },
+            ConvertError::Format { message, .. } => ConvertError::Format { location, message },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for IoOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IoOp::Open => "打开",
+            IoOp::Create => "创建",
+            IoOp::Read => "读取",
+            IoOp::Write => "写入",
+            IoOp::Rename => "替换",
+        })
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Unknown => Ok(()),
+            Location::Offset(offset) => write!(f, "字节偏移 {}", offset),
+            Location::Entry(index) => write!(f, "第 {} 个字符串", index),
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Io { op, path: Some(path), source } => write!(f, "无法{}文件 {}: {}", op, path.display(), source),
+            ConvertError::Io { op, path: None, source } => write!(f, "{}时出错: {}", op, source),
+            ConvertError::BadMagic { found } => write!(f, "MO文件魔数不正确: {:X}, 应为: 950412DE", found),
+            ConvertError::UnsupportedRevision { major, minor } => write!(f, "不支持的MO文件版本: 主版本 {}, 次版本 {}", major, minor),
+            ConvertError::Truncated { offset, len, available } => {
+                write!(f, "文件数据不完整: 需要读取偏移 {} 处的 {} 字节，但文件只有 {} 字节", offset, len, available)
+            }
+            ConvertError::Format { location: Location::Unknown, message } => write!(f, "格式错误: {}", message),
+            ConvertError::Format { location, message } => write!(f, "格式错误 ({}): {}", location, message),
+            ConvertError::Parse(diagnostic) => write!(f, "{}", diagnostic),
+            ConvertError::Encoding { encoding, location: Location::Unknown } => write!(f, "无效的{}字符", encoding),
+            ConvertError::Encoding { encoding, location } => write!(f, "无效的{}字符 ({})", encoding, location),
+            ConvertError::UnsupportedCharset(name) => write!(f, "不支持的字符集: {}", name),
+            ConvertError::PluralForms(message) => write!(f, "Plural-Forms 无效: {}", message),
+            ConvertError::Validation { line: Some(line), message } => write!(f, "第 {} 行: {}", line, message),
+            ConvertError::Validation { line: None, message } => f.write_str(message),
+            ConvertError::Rejected(diagnostics) => match diagnostics.first() {
+                Some(first) => write!(f, "输入文件未通过校验，共 {} 个错误，第一个: {}", diagnostics.len(), first),
+                None => f.write_str("输入文件未通过校验"),
+            },
+            ConvertError::Duplicates(diagnostics) => match diagnostics.first() {
+                Some(first) => write!(f, "存在 {} 个重复的条目，第一个: {}", diagnostics.len(), first),
+                None => f.write_str("存在重复的条目"),
+            },
+            ConvertError::Cancelled => f.write_str("操作已取消"),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
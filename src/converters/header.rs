@@ -0,0 +1,228 @@
+use std::fmt;
+
+use super::catalog;
+use super::charset;
+
+pub const PROJECT_ID_VERSION: &str = "Project-Id-Version";
+pub const POT_CREATION_DATE: &str = "POT-Creation-Date";
+pub const PO_REVISION_DATE: &str = "PO-Revision-Date";
+pub const LAST_TRANSLATOR: &str = "Last-Translator";
+pub const LANGUAGE: &str = "Language";
+pub const MIME_VERSION: &str = "MIME-Version";
+pub const CONTENT_TYPE: &str = "Content-Type";
+pub const CONTENT_TRANSFER_ENCODING: &str = "Content-Transfer-Encoding";
+pub const PLURAL_FORMS: &str = "Plural-Forms";
+pub const GENERATOR: &str = "X-Generator";
+/// 合并输出中记录合并进来的语言包
+pub const MERGED_FROM: &str = "X-Merged-From";
+
+/// 每次生成都会变化的时间戳字段
+pub const TIMESTAMPS: [&str; 2] = [POT_CREATION_DATE, PO_REVISION_DATE];
+
+/// 本工具写入 `X-Generator` 的名称
+pub const GENERATOR_NAME: &str = concat!("Blender字典合并管理器 ", env!("CARGO_PKG_VERSION"));
+
+/// PO头部(msgid "" 条目的译文)
+///
+/// 按行保存原文，未修改的行和字段顺序原样保留，无法识别的行也不会丢失。
+/// 字段名不区分大小写
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Header {
+    lines: Vec<String>,
+    trailing_newline: bool, // 原文是否以换行结尾
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 只声明UTF-8编码的最小头部
+    pub fn minimal() -> Self {
+        let mut header = Self::new();
+        header.set(CONTENT_TYPE, "text/plain; charset=UTF-8");
+        header.set(CONTENT_TRANSFER_ENCODING, "8bit");
+        header
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let trailing_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+        Self {
+            lines: if body.is_empty() && !trailing_newline { Vec::new() } else { body.split('\n').map(str::to_string).collect() },
+            trailing_newline,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// 按顺序列出所有字段 (名称, 值)，不含无法识别的行
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim(), value.trim()))
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// 设置字段的值，已有字段在原位置替换，否则追加到末尾
+    pub fn set(&mut self, name: &str, value: impl AsRef<str>) {
+        let line = format!("{}: {}", name, value.as_ref());
+        match self.position(name) {
+            Some(index) => self.lines[index] = line,
+            None => {
+                self.lines.push(line);
+                self.trailing_newline = true;
+            }
+        }
+    }
+
+    /// 字段不存在时才设置
+    pub fn set_default(&mut self, name: &str, value: impl AsRef<str>) {
+        if self.position(name).is_none() {
+            self.set(name, value);
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.lines.retain(|line| !Self::is_field(line, name));
+    }
+
+    pub fn project_id_version(&self) -> Option<&str> {
+        self.get(PROJECT_ID_VERSION)
+    }
+
+    #[allow(dead_code)]
+    pub fn last_translator(&self) -> Option<&str> {
+        self.get(LAST_TRANSLATOR)
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.get(LANGUAGE).filter(|language| !language.is_empty())
+    }
+
+    #[allow(dead_code)]
+    pub fn generator(&self) -> Option<&str> {
+        self.get(GENERATOR)
+    }
+
+    pub fn plural_forms(&self) -> Option<&str> {
+        self.get(PLURAL_FORMS)
+    }
+
+    /// 复数形式的数量，没有 `Plural-Forms` 或无法解析时为2
+    pub fn nplurals(&self) -> usize {
+        catalog::nplurals_in(self.plural_forms())
+    }
+
+    /// `Content-Type` 中声明的 charset
+    pub fn charset(&self) -> Option<&str> {
+        self.get(CONTENT_TYPE).and_then(charset::header_charset)
+    }
+
+    /// 改写 `Content-Type` 中的 charset，没有 `Content-Type` 时添加
+    pub fn set_charset(&mut self, name: &str) {
+        if self.charset() == Some(name) {
+            return;
+        }
+        let content_type = match self.get(CONTENT_TYPE) {
+            Some(value) if value.contains("charset=") => {
+                let current = charset::header_charset(value).unwrap_or_default();
+                let start = value.find("charset=").unwrap_or(0) + "charset=".len();
+                format!("{}{}{}", &value[..start], name, &value[start + current.len()..])
+            }
+            Some(value) if !value.is_empty() => format!("{}; charset={}", value.trim_end_matches(';'), name),
+            _ => format!("text/plain; charset={}", name),
+        };
+        self.set(CONTENT_TYPE, content_type);
+    }
+
+    /// 时间戳字段改用 `other` 中的值，两边都有的字段才会替换
+    pub fn with_timestamps_from(&self, other: &Header) -> Self {
+        let mut header = self.clone();
+        for name in TIMESTAMPS {
+            if let (Some(_), Some(value)) = (self.get(name), other.get(name)) {
+                header.set(name, value);
+            }
+        }
+        header
+    }
+
+    /// 生成合并输出的头部
+    ///
+    /// 以当前头部为基础: 声明UTF-8编码，没有 `Plural-Forms` 时按 `Language` 补上该语言的标准规则
+    /// (已有的规则不改写，以免与条目中 msgstr[n] 的数量不一致)，`X-Generator` 改为本工具，`X-Merged-From` 按优先级列出合并进来的文件
+    pub fn for_merge_output(&self, merged_from: &[String]) -> Self {
+        let mut header = self.clone();
+        header.set_default(MIME_VERSION, "1.0");
+        header.set_charset("UTF-8");
+        header.set_default(CONTENT_TRANSFER_ENCODING, "8bit");
+        if header.plural_forms().is_none() {
+            if let Some(rule) = header.language().and_then(plural_forms_for_language) {
+                header.set(PLURAL_FORMS, rule);
+            }
+        }
+        header.set(GENERATOR, GENERATOR_NAME);
+        if merged_from.is_empty() {
+            header.remove(MERGED_FROM);
+        } else {
+            header.set(MERGED_FROM, merged_from.join(", "));
+        }
+        header
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.lines.iter().position(|line| Self::is_field(line, name))
+    }
+
+    fn is_field(line: &str, name: &str) -> bool {
+        line.split_once(':')
+            .is_some_and(|(field, _)| field.trim().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lines.join("\n"))?;
+        if self.trailing_newline {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// 语言代码对应的标准 `Plural-Forms`，规则与 gettext 一致
+///
+/// 先按完整代码查找(例如 `pt_BR`)，再按语言部分查找(`zh_HANS` -> `zh`)，未知语言返回None
+pub fn plural_forms_for_language(language: &str) -> Option<&'static str> {
+    let code = language.trim().replace('-', "_");
+    let base = code.split(['_', '@', '.']).next().unwrap_or_default().to_ascii_lowercase();
+    let rule = match (code.as_str(), base.as_str()) {
+        ("pt_BR", _) => "nplurals=2; plural=(n > 1);",
+        (_, "ja" | "ko" | "zh" | "vi" | "th" | "id" | "ms" | "ka") => "nplurals=1; plural=0;",
+        (_, "fr" | "fa" | "hy" | "ab") => "nplurals=2; plural=(n > 1);",
+        (_, "en" | "de" | "nl" | "sv" | "da" | "nb" | "nn" | "no" | "fi" | "et" | "it" | "es" | "pt"
+            | "ca" | "eu" | "gl" | "el" | "hu" | "bg" | "he" | "tr" | "eo" | "af" | "sw" | "ky" | "kk" | "hi") => {
+            "nplurals=2; plural=(n != 1);"
+        }
+        (_, "ru" | "uk" | "be" | "sr" | "hr" | "bs") => {
+            "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);"
+        }
+        (_, "pl") => "nplurals=3; plural=(n==1 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);",
+        (_, "cs" | "sk") => "nplurals=3; plural=(n==1) ? 0 : (n>=2 && n<=4) ? 1 : 2;",
+        (_, "lt") => "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && (n%100<10 || n%100>=20) ? 1 : 2);",
+        (_, "lv") => "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n != 0 ? 1 : 2);",
+        (_, "ro") => "nplurals=3; plural=(n==1 ? 0 : (n==0 || (n%100 > 0 && n%100 < 20)) ? 1 : 2);",
+        (_, "sl") => "nplurals=4; plural=(n%100==1 ? 0 : n%100==2 ? 1 : n%100==3 || n%100==4 ? 2 : 3);",
+        (_, "ar") => "nplurals=6; plural=(n==0 ? 0 : n==1 ? 1 : n==2 ? 2 : n%100>=3 && n%100<=10 ? 3 : n%100>=11 ? 4 : 5);",
+        _ => return None,
+    };
+    Some(rule)
+}
@@ -0,0 +1,444 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::Hasher;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use rayon::prelude::*;
+
+use super::catalog::{Catalog, Entry, MessageKey};
+use super::error::{ConvertError, IoOp};
+use super::header::Header;
+use super::mo_converter::MoConverter;
+use super::mo_file::MoFile;
+use super::po_merger::{self, DuplicatePolicy, MergeOptions, ObsoletePolicy};
+use super::po_parser::{self, Diagnostic, ParseMode};
+use super::po_validator;
+use super::po_writer::{self, SortOrder};
+use super::progress::{MergeControl, MergeProgress};
+
+/// 增量合并缓存
+///
+/// 保存每个输入文件解析后的条目，以文件内容哈希识别；大小和修改时间都没变时不再读取文件。
+/// 同时保存上一次的合并结果，只启用/禁用语言包或调整顺序时，
+/// 只重新处理受影响的语言包中的条目，其余条目直接沿用上次的结果
+#[derive(Default)]
+pub struct MergeCache {
+    files: HashMap<PathBuf, (Fingerprint, Arc<CachedFile>)>,
+    settings: Option<(ObsoletePolicy, ParseMode, DuplicatePolicy, bool)>,
+    merged: Option<MergedState>,
+}
+
+// 文件大小和修改时间，用于快速判断文件是否可能已改变
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+// 已解析的输入文件
+struct CachedFile {
+    content_hash: u64,
+    header: Header,
+    header_comments: Vec<String>,
+    entries: Vec<Entry>,                        // 已按过时条目和重复条目规则处理
+    index: HashMap<MessageKey, Vec<usize>>,     // 键 -> 条目位置，同一键的过时条目和正常条目按顺序排列
+    diagnostics: Vec<Diagnostic>,
+    validation: Vec<Diagnostic>,                // 语言包的校验结果，主MO文件不校验
+}
+
+/// 合并结果
+pub struct MergeOutput {
+    pub catalog: Catalog,
+    pub diagnostics: Vec<Diagnostic>, // 各语言包解析时跳过的问题
+    pub validation: Vec<Diagnostic>,  // 各语言包的校验结果
+}
+
+// 上一次的合并结果
+struct MergedState {
+    inputs: Vec<Arc<CachedFile>>,                         // 按优先级排序的输入
+    winners: HashMap<MessageKey, (Arc<CachedFile>, usize)>, // 每个键选中的条目: 所在文件和位置，不复制条目
+}
+
+impl MergeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 清空缓存，下次合并时重新解析所有文件
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 合并主MO文件和语言包
+    ///
+    /// 语言包按顺序合并(第一个优先级最高)，主MO文件优先级最低，
+    /// 设置了 `ignore_main_entries` 时则最高且其中的条目不会被替换。冲突按 [`po_merger::replaces`] 的规则解决，
+    /// 增量合并与完整合并逐个键使用同一个解决函数，结果总是相同。条目按 msgctxt、msgid 排列，
+    /// 头部以主MO文件的头部为基础生成并记录合并的语言包
+    ///
+    /// 语言包在解析时一并校验，校验结果与解析结果一起缓存
+    ///
+    /// # 返回
+    /// * 合并后的翻译目录、各语言包解析时发现的问题和校验结果(包括沿用缓存的文件)
+    pub fn merge(
+        &mut self,
+        base_mo: &Path,
+        mod_files: &[PathBuf],
+        options: &MergeOptions,
+        control: &MergeControl,
+    ) -> Result<MergeOutput, ConvertError> {
+        // 影响解析和合并结果的选项改变后缓存全部失效
+        let settings = (options.obsolete_policy, options.parse_mode, options.duplicate_policy, options.ignore_main_entries);
+        if self.settings != Some(settings) {
+            self.clear();
+            self.settings = Some(settings);
+        }
+
+        // 已删除的文件不再保留
+        self.files.retain(|path, _| path.exists());
+
+        let mut paths: Vec<&Path> = mod_files.iter().map(PathBuf::as_path).collect();
+        if options.ignore_main_entries {
+            paths.insert(0, base_mo);
+        } else {
+            paths.push(base_mo);
+        }
+
+        let inputs = self.load_files(&paths, base_mo, options, control)?;
+
+        // 有上次的结果时只重新处理受影响的条目，否则处理所有键
+        let (mut winners, keys) = match self.merged.take() {
+            Some(previous) => (previous.winners, affected_keys(&previous.inputs, &inputs)),
+            None => (HashMap::new(), inputs.iter().flat_map(|file| file.index.keys()).cloned().collect()),
+        };
+        resolve_keys(&mut winners, &inputs, keys, options.ignore_main_entries, control)?;
+
+        let base = &self.files[base_mo].1;
+        let mut catalog = Catalog::new();
+        catalog.header = base.header.for_merge_output(&po_merger::file_names(mod_files));
+        catalog.header_comments = base.header_comments.clone();
+        catalog.entries = winners.values().map(|(file, position)| file.entries[*position].clone()).collect();
+        po_writer::sort_entries(&mut catalog.entries, SortOrder::Key);
+
+        let mods: Vec<&CachedFile> = mod_files.iter().map(|path| self.files[path.as_path()].1.as_ref()).collect();
+        let diagnostics = mods.iter().flat_map(|file| file.diagnostics.iter().cloned()).collect();
+        let validation = mods.iter().flat_map(|file| file.validation.iter().cloned()).collect();
+
+        self.merged = Some(MergedState { inputs, winners });
+
+        Ok(MergeOutput { catalog, diagnostics, validation })
+    }
+
+    /// 已缓存文件的翻译目录，条目已按合并选项处理过时条目和重复条目；文件未缓存时返回None
+    pub fn catalog(&self, path: &Path) -> Option<Catalog> {
+        let (_, file) = self.files.get(path)?;
+        Some(Catalog {
+            header: file.header.clone(),
+            header_comments: file.header_comments.clone(),
+            entries: file.entries.clone(),
+        })
+    }
+
+    // 按顺序取得所有输入文件的解析结果，只读取可能改变的文件，只重新解析内容改变的文件
+    fn load_files(
+        &mut self,
+        paths: &[&Path],
+        base_mo: &Path,
+        options: &MergeOptions,
+        control: &MergeControl,
+    ) -> Result<Vec<Arc<CachedFile>>, ConvertError> {
+        let mut stale = Vec::new();
+        for &path in paths {
+            let fingerprint = fingerprint(path)?;
+            match self.files.get(path) {
+                Some((cached, _)) if *cached == fingerprint && fingerprint.modified.is_some() => {}
+                _ => stale.push((path, fingerprint)),
+            }
+        }
+
+        let loaded_count = AtomicUsize::new(0);
+        let loaded = stale.par_iter()
+            .map(|(path, fingerprint)| {
+                control.check()?;
+                let bytes = fs::read(path).map_err(|e| ConvertError::io(IoOp::Read, path, e))?;
+                let content_hash = hash_bytes(&bytes);
+
+                // 内容没有变化时沿用原来的解析结果
+                let file = match self.files.get(*path) {
+                    Some((_, cached)) if cached.content_hash == content_hash => Arc::clone(cached),
+                    _ => Arc::new(parse_file(path, bytes, content_hash, *path == base_mo, options)?),
+                };
+
+                let done = loaded_count.fetch_add(1, Ordering::Relaxed) + 1;
+                control.report(MergeProgress::FilesParsed { done, total: stale.len() });
+                Ok((path.to_path_buf(), (fingerprint.clone(), file)))
+            })
+            .collect::<Result<Vec<_>, ConvertError>>()?;
+        self.files.extend(loaded);
+
+        Ok(paths.iter().map(|path| Arc::clone(&self.files[*path].1)).collect())
+    }
+}
+
+fn fingerprint(path: &Path) -> Result<Fingerprint, ConvertError> {
+    let metadata = fs::metadata(path).map_err(|e| ConvertError::io(IoOp::Open, path, e))?;
+    Ok(Fingerprint {
+        len: metadata.len(),
+        modified: metadata.modified().ok(),
+    })
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+// 解析MO或PO文件内容，并按选项预先处理过时条目和重复条目
+fn parse_file(path: &Path, bytes: Vec<u8>, content_hash: u64, is_mo: bool, options: &MergeOptions) -> Result<CachedFile, ConvertError> {
+    let file_name = path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let (catalog, diagnostics, validation) = if is_mo {
+        (MoConverter::catalog_from_mo(&MoFile::from_bytes(bytes)?, &file_name)?, Vec::new(), Vec::new())
+    } else {
+        let result = po_parser::parse_reader(Cursor::new(bytes), &file_name, options.parse_mode)?;
+        let validation = po_validator::validate_catalog(&result.catalog, &file_name);
+        (result.catalog, result.diagnostics, validation)
+    };
+
+    let entries = po_merger::apply_obsolete_policy(catalog.entries, options.obsolete_policy);
+    let entries = po_merger::apply_duplicate_policy(entries, options.duplicate_policy)?;
+    let mut index: HashMap<MessageKey, Vec<usize>> = HashMap::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        index.entry(entry.key()).or_default().push(i);
+    }
+
+    Ok(CachedFile {
+        content_hash,
+        header: catalog.header,
+        header_comments: catalog.header_comments,
+        entries,
+        index,
+        diagnostics,
+        validation,
+    })
+}
+
+// 按优先级顺序重新决定 `keys` 中每个键的条目，没有任何文件包含的键从结果中移除
+fn resolve_keys(
+    winners: &mut HashMap<MessageKey, (Arc<CachedFile>, usize)>,
+    inputs: &[Arc<CachedFile>],
+    keys: HashSet<MessageKey>,
+    ignore_main_entries: bool,
+    control: &MergeControl,
+) -> Result<(), ConvertError> {
+    let total = keys.len();
+    for (done, key) in keys.into_iter().enumerate() {
+        if done % 1024 == 0 {
+            control.check()?;
+            control.report(MergeProgress::EntriesResolved { done, total });
+        }
+        match resolve_key(inputs, &key, ignore_main_entries) {
+            Some(winner) => winners.insert(key, winner),
+            None => winners.remove(&key),
+        };
+    }
+    control.report(MergeProgress::EntriesResolved { done: total, total });
+    Ok(())
+}
+
+// 依次读取各文件中该键的条目，按冲突规则选出一个，返回所在文件和位置
+fn resolve_key(inputs: &[Arc<CachedFile>], key: &MessageKey, ignore_main_entries: bool) -> Option<(Arc<CachedFile>, usize)> {
+    let mut winner: Option<(usize, usize)> = None;
+    for (file_index, file) in inputs.iter().enumerate() {
+        for &position in file.index.get(key).into_iter().flatten() {
+            let existing = winner.map(|(index, position)| (index, &inputs[index].entries[position]));
+            if po_merger::replaces(existing, &file.entries[position], ignore_main_entries) {
+                winner = Some((file_index, position));
+            }
+        }
+    }
+    winner.map(|(index, position)| (Arc::clone(&inputs[index]), position))
+}
+
+// 两次合并之间受影响的键: 不在新旧输入最长公共子序列中的文件(新增、移除、内容改变或顺序改变)包含的键
+//
+// 其余文件的相对顺序不变，只出现在这些文件中的键的合并结果也不会变
+fn affected_keys(previous: &[Arc<CachedFile>], current: &[Arc<CachedFile>]) -> HashSet<MessageKey> {
+    let (kept_previous, kept_current) = common_subsequence(previous, current);
+
+    let mut keys = HashSet::new();
+    for (files, kept) in [(previous, kept_previous), (current, kept_current)] {
+        for (file, kept) in files.iter().zip(kept) {
+            if !kept {
+                keys.extend(file.index.keys().cloned());
+            }
+        }
+    }
+    keys
+}
+
+// 以文件内容为标识求最长公共子序列，返回两边各文件是否在子序列中
+fn common_subsequence(a: &[Arc<CachedFile>], b: &[Arc<CachedFile>]) -> (Vec<bool>, Vec<bool>) {
+    let same = |x: &Arc<CachedFile>, y: &Arc<CachedFile>| Arc::ptr_eq(x, y);
+
+    // lengths[i][j]: a[i..] 与 b[j..] 的最长公共子序列长度
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if same(&a[i], &b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut kept_a = vec![false; a.len()];
+    let mut kept_b = vec![false; b.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if same(&a[i], &b[j]) {
+            kept_a[i] = true;
+            kept_b[j] = true;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    (kept_a, kept_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converters::po_converter::{CompileOptions, PoConverter};
+
+    const HEADER: &str = "msgid \"\"\nmsgstr \"Content-Type: text/plain; charset=UTF-8\\n\"\n\n";
+
+    // 测试用的临时目录，结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("merge_cache_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, body: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, format!("{}{}", HEADER, body)).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write_base_mo(dir: &TempDir) -> PathBuf {
+        let mut catalog = Catalog::new();
+        catalog.header = Header::minimal();
+        for (msgid, msgstr) in [("Open", "打开(主)"), ("Save", "保存(主)"), ("Close", "关闭(主)")] {
+            catalog.entries.push(Entry::new(None, msgid.to_string(), msgstr.to_string()));
+        }
+        let mut bytes = Vec::new();
+        PoConverter::write_mo_file(&mut bytes, &catalog, &CompileOptions::default()).unwrap();
+        let path = dir.0.join("base.mo");
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn merged(cache: &mut MergeCache, base: &Path, mods: &[PathBuf], options: &MergeOptions) -> Vec<Entry> {
+        cache.merge(base, mods, options, &MergeControl::default()).unwrap().catalog.entries
+    }
+
+    fn translation<'a>(entries: &'a [Entry], msgid: &str) -> Option<&'a str> {
+        entries.iter().find(|e| e.msgctxt.is_none() && e.msgid == msgid && !e.obsolete).map(Entry::msgstr)
+    }
+
+    #[test]
+    fn incremental_merge_matches_full_merge() {
+        let dir = TempDir::new("incremental");
+        let base = write_base_mo(&dir);
+        let a = dir.write("a.po", "#, fuzzy\nmsgid \"Open\"\nmsgstr \"打开(a)\"\n\nmsgid \"Save\"\nmsgstr \"保存(a)\"\n\nmsgctxt \"Menu\"\nmsgid \"Open\"\nmsgstr \"打开菜单(a)\"\n");
+        let b = dir.write("b.po", "msgid \"Open\"\nmsgstr \"打开(b)\"\n\nmsgid \"Save\"\nmsgstr \"\"\n\n#~ msgid \"Close\"\n#~ msgstr \"关闭(b)\"\n\n#~ msgid \"Old\"\n#~ msgstr \"旧(b)\"\n");
+        let c = dir.write("c.po", "msgid \"Quit\"\nmsgstr \"退出(c)\"\n\nmsgid \"Save\"\nmsgstr \"保存(c)\"\n\nmsgid \"Old\"\nmsgstr \"旧(c)\"\n");
+
+        let configurations: Vec<Vec<PathBuf>> = vec![
+            vec![a.clone(), b.clone(), c.clone()],
+            vec![b.clone(), a.clone(), c.clone()],
+            vec![a.clone(), c.clone()],
+            vec![c.clone(), a.clone(), b.clone()],
+            vec![],
+            vec![b.clone()],
+            vec![a.clone(), b.clone(), c.clone()],
+        ];
+
+        for ignore_main_entries in [false, true] {
+            let options = MergeOptions { ignore_main_entries, obsolete_policy: ObsoletePolicy::Keep, ..Default::default() };
+            let mut cache = MergeCache::new();
+            for mods in &configurations {
+                let incremental = merged(&mut cache, &base, mods, &options);
+                let full = merged(&mut MergeCache::new(), &base, mods, &options);
+                assert_eq!(incremental, full, "mods: {:?}, ignore_main_entries: {}", mods, ignore_main_entries);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_merge_picks_up_changed_content() {
+        let dir = TempDir::new("changed");
+        let base = write_base_mo(&dir);
+        let a = dir.write("a.po", "msgid \"Open\"\nmsgstr \"打开(a)\"\n");
+        let b = dir.write("b.po", "msgid \"Save\"\nmsgstr \"保存(b)\"\n");
+        let options = MergeOptions::default();
+
+        let mut cache = MergeCache::new();
+        merged(&mut cache, &base, &[a.clone(), b.clone()], &options);
+        dir.write("a.po", "msgid \"Close\"\nmsgstr \"关闭(a), 内容已修改\"\n");
+        let incremental = merged(&mut cache, &base, &[a.clone(), b.clone()], &options);
+        assert_eq!(incremental, merged(&mut MergeCache::new(), &base, &[a, b], &options));
+        assert_eq!(translation(&incremental, "Open"), Some("打开(主)"));
+        assert_eq!(translation(&incremental, "Close"), Some("关闭(a), 内容已修改"));
+    }
+
+    #[test]
+    fn conflicts_follow_priority_rules() {
+        let dir = TempDir::new("priority");
+        let base = write_base_mo(&dir);
+        let a = dir.write("a.po", "#, fuzzy\nmsgid \"Open\"\nmsgstr \"打开(a)\"\n\nmsgid \"Save\"\nmsgstr \"保存(a)\"\n\n#~ msgid \"Close\"\n#~ msgstr \"关闭(a)\"\n");
+        let b = dir.write("b.po", "msgid \"Open\"\nmsgstr \"打开(b)\"\n\nmsgid \"Save\"\nmsgstr \"保存(b)\"\n");
+
+        let options = MergeOptions { obsolete_policy: ObsoletePolicy::Keep, ..Default::default() };
+        let entries = merged(&mut MergeCache::new(), &base, &[a.clone(), b.clone()], &options);
+        // 模糊条目被优先级更低的完整翻译替换
+        assert_eq!(translation(&entries, "Open"), Some("打开(b)"));
+        // 优先级高的完整翻译优先
+        assert_eq!(translation(&entries, "Save"), Some("保存(a)"));
+        // 过时条目不替换正常条目
+        assert_eq!(translation(&entries, "Close"), Some("关闭(主)"));
+        assert!(entries.iter().all(|e| !e.obsolete));
+
+        let options = MergeOptions { ignore_main_entries: true, ..options };
+        let entries = merged(&mut MergeCache::new(), &base, &[a, b], &options);
+        // 主MO文件中已有的条目不被替换
+        assert_eq!(translation(&entries, "Open"), Some("打开(主)"));
+        assert_eq!(translation(&entries, "Save"), Some("保存(主)"));
+    }
+}
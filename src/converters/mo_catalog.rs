@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use super::error::ConvertError;
+use super::header::Header;
+use super::mo_file::MoFile;
+use super::plural::PluralForms;
+
+/// 已编译MO文件的查询接口
+///
+/// 按 gettext 运行时的规则查找译文: 找不到或译文为空时返回原文，
+/// 复数条目根据头部的 `Plural-Forms` 表达式选择译文。
+/// 译文直接从MO文件内容中读取，不会为每个条目分配字符串
+pub struct MoCatalog {
+    mo: MoFile,
+    plural_forms: PluralForms,
+}
+
+impl MoCatalog {
+    /// 加载MO文件
+    ///
+    /// 文件内容一次性读入内存而不是内存映射，预览期间不会锁定文件
+    pub fn open(path: &Path) -> Result<Self, ConvertError> {
+        let mo = MoFile::read(path)?;
+
+        let header = Header::parse(&mo.header_text()?.unwrap_or_default());
+        let plural_forms = match header.plural_forms() {
+            Some(value) => PluralForms::parse(value)?,
+            None => PluralForms::default(),
+        };
+
+        Ok(Self { mo, plural_forms })
+    }
+
+    /// 条目数量(不含头部)
+    pub fn len(&self) -> usize {
+        match self.mo.header_text() {
+            Ok(Some(_)) => self.mo.len() - 1,
+            _ => self.mo.len(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn plural_forms(&self) -> &PluralForms {
+        &self.plural_forms
+    }
+
+    pub fn gettext<'a>(&'a self, msgid: &'a str) -> Cow<'a, str> {
+        self.translate(None, msgid)
+    }
+
+    pub fn pgettext<'a>(&'a self, msgctxt: &str, msgid: &'a str) -> Cow<'a, str> {
+        self.translate(Some(msgctxt), msgid)
+    }
+
+    pub fn ngettext<'a>(&'a self, msgid: &'a str, msgid_plural: &'a str, n: u64) -> Cow<'a, str> {
+        self.translate_plural(None, msgid, msgid_plural, n)
+    }
+
+    pub fn npgettext<'a>(&'a self, msgctxt: &str, msgid: &'a str, msgid_plural: &'a str, n: u64) -> Cow<'a, str> {
+        self.translate_plural(Some(msgctxt), msgid, msgid_plural, n)
+    }
+
+    // 查找第 `index` 种译文，不存在或为空时返回None
+    fn lookup(&self, msgctxt: Option<&str>, msgid: &str, index: usize) -> Option<Cow<'_, str>> {
+        let entry = self.mo.entry(self.mo.find(msgctxt, msgid)?).ok()?;
+        entry.msgstr.into_iter().nth(index).filter(|msgstr| !msgstr.is_empty())
+    }
+
+    fn translate<'a>(&'a self, msgctxt: Option<&str>, msgid: &'a str) -> Cow<'a, str> {
+        self.lookup(msgctxt, msgid, 0).unwrap_or(Cow::Borrowed(msgid))
+    }
+
+    // 没有译文时与 gettext 一样按英语规则在单数和复数原文之间选择
+    fn translate_plural<'a>(&'a self, msgctxt: Option<&str>, msgid: &'a str, msgid_plural: &'a str, n: u64) -> Cow<'a, str> {
+        self.lookup(msgctxt, msgid, self.plural_forms.index(n))
+            .unwrap_or(Cow::Borrowed(if n == 1 { msgid } else { msgid_plural }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converters::catalog::{Catalog, Entry};
+    use crate::converters::po_converter::{CompileOptions, PoConverter};
+
+    #[test]
+    fn plural_lookup_uses_plural_forms() {
+        let mut catalog = Catalog::new();
+        catalog.header = Header::parse("Content-Type: text/plain; charset=UTF-8\nPlural-Forms: nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);\n");
+        catalog.entries.push(Entry {
+            msgid: "%d file".to_string(),
+            msgid_plural: Some("%d files".to_string()),
+            msgstr: vec!["%d файл".to_string(), "%d файла".to_string(), "%d файлов".to_string()],
+            ..Default::default()
+        });
+        catalog.entries.push(Entry::new(Some("Menu".to_string()), "Open".to_string(), "Открыть".to_string()));
+        catalog.entries.push(Entry::new(None, "Close".to_string(), String::new()));
+
+        let path = std::env::temp_dir().join(format!("mo_catalog_test_{}.mo", std::process::id()));
+        let mut bytes = Vec::new();
+        PoConverter::write_mo_file(&mut bytes, &catalog, &CompileOptions { include_untranslated: true, ..Default::default() }).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        let mo = MoCatalog::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mo = mo.unwrap();
+
+        assert_eq!(mo.len(), 3);
+        assert_eq!(mo.plural_forms().nplurals, 3);
+        assert_eq!(mo.ngettext("%d file", "%d files", 1), "%d файл");
+        assert_eq!(mo.ngettext("%d file", "%d files", 3), "%d файла");
+        assert_eq!(mo.ngettext("%d file", "%d files", 11), "%d файлов");
+        assert_eq!(mo.ngettext("%d file", "%d files", 21), "%d файл");
+        assert_eq!(mo.pgettext("Menu", "Open"), "Открыть");
+        // 没有译文时返回原文，复数按英语规则选择
+        assert_eq!(mo.gettext("Open"), "Open");
+        assert_eq!(mo.gettext("Close"), "Close");
+        assert_eq!(mo.ngettext("%d dir", "%d dirs", 1), "%d dir");
+        assert_eq!(mo.ngettext("%d dir", "%d dirs", 2), "%d dirs");
+    }
+}
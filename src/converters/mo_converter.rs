@@ -1,8 +1,116 @@
-use std::fs::File;
-use std::io::{Read, Write, BufWriter};
+use std::io::Write;
 use std::path::Path;
 use rayon::prelude::*;
 
+use serde::{Serialize, Deserialize};
+
+use super::catalog::Catalog;
+use super::error::ConvertError;
+use super::header::Header;
+use super::mo_file::MoFile;
+use super::po_writer::{self, WriteOptions};
+
+/// MO文件魔数
+pub const MO_MAGIC: u32 = 0x9504_12DE;
+/// MO文件头大小
+pub const MO_HEADER_SIZE: usize = 28;
+
+// MO文件没有头部时，转换出的PO文件使用的头部
+const DEFAULT_PO_HEADER: &str = "Content-Type: text/plain; charset=UTF-8\nContent-Transfer-Encoding: 8bit\nPlural-Forms: nplurals=2; plural=(n != 1);\n";
+
+/// MO文件的字节序
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ByteOrder {
+    #[default]
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    /// 从缓冲区的指定偏移读取一个u32
+    pub fn read_u32(self, buffer: &[u8], offset: usize) -> u32 {
+        let bytes: [u8; 4] = buffer[offset..offset + 4].try_into().unwrap();
+        match self {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+    
+    /// 将u32按该字节序转换为字节
+    pub fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        }
+    }
+}
+
+/// MO文件头
+#[derive(Debug, Clone, Copy)]
+pub struct MoHeader {
+    pub byte_order: ByteOrder,
+    #[allow(dead_code)]
+    pub revision: u32,
+    pub num_strings: u32,
+    pub original_table_offset: u32,
+    pub translation_table_offset: u32,
+    pub hash_table_size: u32,
+    pub hash_table_offset: u32,
+}
+
+/// gettext 使用的 hashpjw 字符串哈希
+/// 
+/// 与 gettext 一致，只对第一个NUL之前的部分(即 msgctxt+msgid，不含复数原文)计算哈希
+pub fn hash_string(bytes: &[u8]) -> u32 {
+    let mut hval: u32 = 0;
+    for &c in bytes.iter().take_while(|&&c| c != 0) {
+        hval = (hval << 4).wrapping_add(c as u32);
+        let g = hval & (0xF << 28);
+        if g != 0 {
+            hval ^= g >> 24;
+            hval ^= g;
+        }
+    }
+    hval
+}
+
+/// 计算 `num_strings` 个条目所需的哈希表大小: 不小于 4n/3 的素数，最小为3
+pub fn hash_table_size(num_strings: usize) -> u32 {
+    let mut size = ((num_strings * 4 / 3) as u32) | 1;
+    while !is_prime(size) {
+        size += 2;
+    }
+    size.max(3)
+}
+
+fn is_prime(n: u32) -> bool {
+    if n < 2 {
+        return false;
+    }
+    let mut divisor = 2u32;
+    while divisor * divisor <= n {
+        if n % divisor == 0 {
+            return false;
+        }
+        divisor += 1;
+    }
+    true
+}
+
+/// 哈希表的开放寻址探测序列: 起始位置和步长
+pub fn hash_probe(hash: u32, size: u32) -> (u32, u32) {
+    (hash % size, 1 + hash % (size - 2))
+}
+
+/// 探测序列中的下一个位置
+pub fn hash_next(index: u32, increment: u32, size: u32) -> u32 {
+    if index >= size - increment {
+        index - (size - increment)
+    } else {
+        index + increment
+    }
+}
+
 pub struct MoConverter;
 
 impl MoConverter {
@@ -19,165 +127,84 @@ impl MoConverter {
     pub fn convert_mo_to_po(
         input: &Path, 
         output: &Path
-    ) -> Result<(), String> {
-        // 读取MO文件
-        let mut buffer = Vec::new();
-        let mut file = File::open(input).map_err(|e| format!("无法打开MO文件: {}", e))?;
-        file.read_to_end(&mut buffer).map_err(|e| format!("无法读取MO文件内容: {}", e))?;
-        
-        // 解析 .mo 头部
-        if buffer.len() < 20 {
-            return Err("MO文件格式不正确或文件太小".to_string());
-        }
-        
-        // 检查Magic Number (0x950412DE for little-endian)
-        let magic = u32::from_le_bytes(buffer[0..4].try_into().unwrap());
-        if magic != 0x9504_12DE {
-            return Err(format!("MO文件魔数不正确: {:X}, 应为: 950412DE", magic));
-        }
-        
-        let num_strings = u32::from_le_bytes(buffer[8..12].try_into().unwrap());
-        let original_table_offset = u32::from_le_bytes(buffer[12..16].try_into().unwrap());
-        let translation_table_offset = u32::from_le_bytes(buffer[16..20].try_into().unwrap());
+    ) -> Result<(), ConvertError> {
+        Self::convert_mo_to_po_with(input, output, &WriteOptions::default())
+    }
 
-        let file = File::create(output).map_err(|e| format!("无法创建PO输出文件: {}", e))?;
-        let mut writer = BufWriter::new(file);
+    /// 按指定写出选项将MO文件转换为PO文件
+    pub fn convert_mo_to_po_with(input: &Path, output: &Path, options: &WriteOptions) -> Result<(), ConvertError> {
+        let mo = MoFile::open(input)?;
+        po_writer::write_po_file(&Self::po_catalog(&mo)?, output, options)
+    }
 
-        // 使用Rayon并行处理所有条目
-        let entries = (0..num_strings).into_par_iter().map(|i| {
-            let orig_offset = original_table_offset + i * 8;
-            let trans_offset = translation_table_offset + i * 8;
-            
-            if orig_offset as usize + 8 > buffer.len() || trans_offset as usize + 8 > buffer.len() {
-                return Err(format!("MO文件格式错误: 偏移量超出文件大小"));
-            }
-            
-            let orig_len = u32::from_le_bytes(buffer[orig_offset as usize..orig_offset as usize + 4].try_into().unwrap());
-            let orig_str_offset = u32::from_le_bytes(buffer[orig_offset as usize + 4..orig_offset as usize + 8].try_into().unwrap());
-            
-            let trans_len = u32::from_le_bytes(buffer[trans_offset as usize..trans_offset as usize + 4].try_into().unwrap());
-            let trans_str_offset = u32::from_le_bytes(buffer[trans_offset as usize + 4..trans_offset as usize + 8].try_into().unwrap());
-            
-            if (orig_str_offset + orig_len) as usize > buffer.len() || (trans_str_offset + trans_len) as usize > buffer.len() {
-                return Err(format!("MO文件格式错误: 字符串偏移量超出文件大小"));
-            }
-            
-            let orig = match String::from_utf8(buffer[orig_str_offset as usize..(orig_str_offset + orig_len) as usize].to_vec()) {
-                Ok(s) => s,
-                Err(_) => return Err(format!("MO文件包含无效的UTF-8字符串")),
-            };
-            
-            let (msgctxt, orig_text) = if let Some(idx) = orig.find('\x04') {
-                let (ctx, text) = orig.split_at(idx);
-                (Some(ctx.to_string()), text[1..].to_string())
-            } else {
-                (None, orig)
-            };
-            
-            let trans = match String::from_utf8(buffer[trans_str_offset as usize..(trans_str_offset + trans_len) as usize].to_vec()) {
-                Ok(s) => s,
-                Err(_) => return Err(format!("MO文件包含无效的UTF-8字符串")),
-            };
-            
-            Ok(MoEntry { msgctxt, orig_text: orig_text, trans_text: trans })
-        }).collect::<Result<Vec<_>, String>>()?;
-        
-        // 首先处理头部信息
-        let mut has_header = false;
-        for entry in &entries {
-            if entry.orig_text.is_empty() {
-                // 写入PO文件头
-                writeln!(writer, "msgid \"\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-                writeln!(writer, "msgstr \"\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-                
-                // 处理头部信息
-                for line in entry.trans_text.lines() {
-                    let escaped = Self::escape_po_string(line);
-                    writeln!(writer, "\"{}\\n\"", escaped).map_err(|e| format!("写入PO文件失败: {}", e))?;
-                }
-                
-                writeln!(writer).map_err(|e| format!("写入PO文件失败: {}", e))?;
-                
-                has_header = true;
-                break;
-            }
-        }
+    /// 读取MO文件为翻译目录
+    /// 
+    /// # Arguments
+    /// 
+    /// * `input` - MO文件路径
+    /// 
+    /// # Returns
+    /// 
+    /// 成功返回翻译目录，头部条目(空msgid)存入`Catalog::header`
+    pub fn read_mo_file(input: &Path) -> Result<Catalog, ConvertError> {
+        let mo = MoFile::open(input)?;
         
-        // 如果没有头部，创建一个标准头部
-        if !has_header {
-            writeln!(writer, "msgid \"\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-            writeln!(writer, "msgstr \"\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-            writeln!(writer, "\"Content-Type: text/plain; charset=UTF-8\\n\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-            writeln!(writer, "\"Content-Transfer-Encoding: 8bit\\n\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-            writeln!(writer, "\"Plural-Forms: nplurals=2; plural=(n != 1);\\n\"").map_err(|e| format!("写入PO文件失败: {}", e))?;
-            writeln!(writer).map_err(|e| format!("写入PO文件失败: {}", e))?;
-        }
+        let source_file = input.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
         
-        // 跳过空条目，写入其他所有条目
-        for entry in entries {
-            if entry.orig_text.is_empty() {
-                continue; // 已经处理过头部了
-            }
-            
-            // 写入msgctxt(如果存在)
-            if let Some(ctx) = &entry.msgctxt {
-                Self::write_po_string(&mut writer, "msgctxt", ctx)?;
-            }
-            
-            // 写入msgid
-            Self::write_po_string(&mut writer, "msgid", &entry.orig_text)?;
-            
-            // 写入msgstr
-            Self::write_po_string(&mut writer, "msgstr", &entry.trans_text)?;
-            
-            writeln!(writer).map_err(|e| format!("写入PO文件失败: {}", e))?;
-        }
-        
-        Ok(())
+        Self::catalog_from_mo(&mo, &source_file)
     }
     
-    /// 写入PO格式的字符串
-    fn write_po_string<W: Write>(writer: &mut W, prefix: &str, content: &str) -> Result<(), String> {
-        let escaped = Self::escape_po_string(content);
+    /// 将已打开的MO文件转换为翻译目录，`source_file` 用作条目的来源文件
+    pub fn catalog_from_mo(mo: &MoFile, source_file: &str) -> Result<Catalog, ConvertError> {
+        // 使用Rayon并行处理所有条目
+        let entries = (0..mo.len()).into_par_iter()
+            .map(|i| mo.entry(i).map(|entry| entry.to_entry(source_file)))
+            .collect::<Result<Vec<_>, ConvertError>>()?;
         
-        if content.contains('\n') || content.len() > 80 {
-            // 长字符串或多行文本使用空引号行格式
-            writeln!(writer, "{} \"\"", prefix).map_err(|e| format!("写入PO文件失败: {}", e))?;
-            
-            for line in escaped.lines() {
-                writeln!(writer, "\"{}\\n\"", line).map_err(|e| format!("写入PO文件失败: {}", e))?;
+        // 空msgid的条目为头部信息，字符串已转为UTF-8，头部同步改写
+        let mut catalog = Catalog::new();
+        for entry in entries {
+            if entry.msgid.is_empty() && entry.msgctxt.is_none() {
+                catalog.header = Header::parse(entry.msgstr());
+                if catalog.header.charset().is_some() {
+                    catalog.header.set_charset("UTF-8");
+                }
+            } else {
+                catalog.entries.push(entry);
             }
-        } else {
-            // 短字符串直接写在一行
-            writeln!(writer, "{} \"{}\"", prefix, escaped).map_err(|e| format!("写入PO文件失败: {}", e))?;
         }
         
-        Ok(())
+        Ok(catalog)
     }
     
-    /// 转义PO文件中的字符串
-    /// 
-    /// # Arguments
-    /// 
-    /// * `s` - 要转义的字符串
+    /// 检查MO文件哈希表的一致性
     /// 
     /// # Returns
     /// 
-    /// 转义后的字符串
-    fn escape_po_string(s: &str) -> String {
-        s.replace('\\', "\\\\")
-         .replace('\"', "\\\"")
-         .replace('\r', "\\r")
-         .replace('\t', "\\t")
-         // 注意我们不替换'\n'，因为多行处理已经单独处理了换行
+    /// 文件不含哈希表时返回Ok(false)；哈希表中每个原文都能被查找到自身时返回Ok(true)，
+    /// 否则返回描述第一个问题的Err
+    pub fn verify_hash_table(input: &Path) -> Result<bool, ConvertError> {
+        MoFile::open(input)?.verify_hash_table()
+    }
+    
+    /// 将MO文件以PO格式写入
+    ///
+    /// 字符串按原样写出(只做转义和折行)，重新编译后条目内容与原MO文件逐字节相同
+    pub fn write_po<W: Write>(writer: &mut W, mo: &MoFile, options: &WriteOptions) -> Result<(), ConvertError> {
+        po_writer::write_catalog(writer, &Self::po_catalog(mo)?, options)
+    }
+    
+    // 转换为PO时使用的翻译目录，没有头部时使用一个标准头部
+    fn po_catalog(mo: &MoFile) -> Result<Catalog, ConvertError> {
+        let mut catalog = Self::catalog_from_mo(mo, "")?;
+        if catalog.header.is_empty() {
+            catalog.header = Header::parse(DEFAULT_PO_HEADER);
+        }
+        Ok(catalog)
     }
-}
-
-// MO条目结构体，用于并行处理
-struct MoEntry {
-    msgctxt: Option<String>,
-    orig_text: String,
-    trans_text: String,
 }
 
 /// 命令行工具入口点
@@ -0,0 +1,378 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use encoding_rs::{Encoding, UTF_8};
+use memmap2::Mmap;
+
+use super::catalog::Entry;
+use super::charset;
+use super::error::{ConvertError, IoOp, Location};
+use super::mo_converter::{self, ByteOrder, MoHeader, MO_HEADER_SIZE, MO_MAGIC};
+
+/// 支持的最高主版本号
+const MO_MAX_MAJOR_REVISION: u32 = 1;
+
+// MO文件内容: 内存映射或一次性读入的缓冲区
+enum MoData {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl MoData {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            MoData::Mapped(mmap) => mmap,
+            MoData::Owned(buffer) => buffer,
+        }
+    }
+}
+
+/// 只读的MO文件
+///
+/// 条目按需从文件内容中解析，UTF-8文件的字符串直接借用文件内容，不再复制
+pub struct MoFile {
+    data: MoData,
+    header: MoHeader,
+    encoding: &'static Encoding,
+}
+
+/// MO文件中的一个条目，字符串借用自 `MoFile`
+#[derive(Debug, Clone)]
+pub struct MoEntry<'a> {
+    pub msgctxt: Option<Cow<'a, str>>,
+    pub msgid: Cow<'a, str>,
+    pub msgid_plural: Option<Cow<'a, str>>,
+    pub msgstr: Vec<Cow<'a, str>>,
+}
+
+impl MoEntry<'_> {
+    /// 是否为头部条目(msgid为空且没有上下文)
+    pub fn is_header(&self) -> bool {
+        self.msgid.is_empty() && self.msgctxt.is_none()
+    }
+
+    /// 转换为拥有所有权的翻译条目
+    pub fn to_entry(&self, source_file: &str) -> Entry {
+        Entry {
+            msgctxt: self.msgctxt.as_ref().map(|s| s.to_string()),
+            msgid: self.msgid.to_string(),
+            msgid_plural: self.msgid_plural.as_ref().map(|s| s.to_string()),
+            msgstr: self.msgstr.iter().map(|s| s.to_string()).collect(),
+            source_file: source_file.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl MoFile {
+    /// 以内存映射方式打开MO文件，映射失败时回退为一次性读入
+    ///
+    /// 映射期间文件在Windows上不能被覆盖，需要长期持有时应使用 `read`
+    pub fn open(path: &Path) -> Result<Self, ConvertError> {
+        let file = File::open(path).map_err(|e| ConvertError::io(IoOp::Open, path, e))?;
+
+        // 安全性: 映射期间文件被其他程序修改只会读到不一致的内容，
+        // 所有偏移在使用前都会做边界检查
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => Self::from_data(MoData::Mapped(mmap)),
+            Err(_) => Self::read_from(file, path),
+        }
+    }
+
+    /// 将MO文件一次性读入内存
+    pub fn read(path: &Path) -> Result<Self, ConvertError> {
+        let file = File::open(path).map_err(|e| ConvertError::io(IoOp::Open, path, e))?;
+        Self::read_from(file, path)
+    }
+
+    /// 从内存中的MO内容构建
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, ConvertError> {
+        Self::from_data(MoData::Owned(bytes))
+    }
+
+    fn read_from(mut file: File, path: &Path) -> Result<Self, ConvertError> {
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).map_err(|e| ConvertError::io(IoOp::Read, path, e))?;
+        Self::from_bytes(buffer)
+    }
+
+    fn from_data(data: MoData) -> Result<Self, ConvertError> {
+        let header = Self::parse_header(data.bytes())?;
+        let mut file = Self { data, header, encoding: UTF_8 };
+
+        // 头部条目(空msgid)中声明的字符集决定所有字符串的编码
+        file.encoding = file.header_encoding()?;
+        Ok(file)
+    }
+
+    /// 解析MO文件头，根据魔数判断字节序并检查文件格式版本
+    fn parse_header(buffer: &[u8]) -> Result<MoHeader, ConvertError> {
+        if buffer.len() < MO_HEADER_SIZE {
+            return Err(ConvertError::Truncated { offset: 0, len: MO_HEADER_SIZE, available: buffer.len() });
+        }
+
+        // 检查Magic Number: 0x950412DE 按小端读取为原值，大端文件则为字节反转后的值
+        let magic = u32::from_le_bytes(buffer[0..4].try_into().unwrap());
+        let byte_order = if magic == MO_MAGIC {
+            ByteOrder::Little
+        } else if magic == MO_MAGIC.swap_bytes() {
+            ByteOrder::Big
+        } else {
+            return Err(ConvertError::BadMagic { found: magic });
+        };
+
+        // 主版本号只支持0和1，次版本号不影响读取
+        let revision = byte_order.read_u32(buffer, 4);
+        let major = revision >> 16;
+        if major > MO_MAX_MAJOR_REVISION {
+            return Err(ConvertError::UnsupportedRevision { major, minor: revision & 0xFFFF });
+        }
+
+        Ok(MoHeader {
+            byte_order,
+            revision,
+            num_strings: byte_order.read_u32(buffer, 8),
+            original_table_offset: byte_order.read_u32(buffer, 12),
+            translation_table_offset: byte_order.read_u32(buffer, 16),
+            hash_table_size: byte_order.read_u32(buffer, 20),
+            hash_table_offset: byte_order.read_u32(buffer, 24),
+        })
+    }
+
+    // 读取头部条目中声明的字符集，头部总是排序后的第一个条目，其 charset 为ASCII文本
+    fn header_encoding(&self) -> Result<&'static Encoding, ConvertError> {
+        if self.header.num_strings == 0 || !self.original_bytes(0)?.is_empty() {
+            return Ok(UTF_8);
+        }
+
+        let header = String::from_utf8_lossy(self.translation_bytes(0)?);
+        charset::encoding_for_charset(charset::header_charset(&header))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.data.bytes()
+    }
+
+    /// 文件头信息
+    #[allow(dead_code)]
+    pub fn header(&self) -> &MoHeader {
+        &self.header
+    }
+
+    /// 字符串使用的编码
+    #[allow(dead_code)]
+    pub fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// 条目数量(含头部条目)
+    pub fn len(&self) -> usize {
+        self.header.num_strings as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.num_strings == 0
+    }
+
+    // 读取字符串表中第 `index` 项指向的字节，不含结尾的NUL
+    fn string_bytes(&self, table_offset: u32, index: usize) -> Result<&[u8], ConvertError> {
+        let buffer = self.bytes();
+        let order = self.header.byte_order;
+        if index >= self.len() {
+            return Err(ConvertError::format(Location::Entry(index), format!("条目序号超出范围 (共 {} 个)", self.len())));
+        }
+        let offset = table_offset as usize + index * 8;
+        if offset + 8 > buffer.len() {
+            return Err(ConvertError::Truncated { offset, len: 8, available: buffer.len() });
+        }
+
+        let length = order.read_u32(buffer, offset) as usize;
+        let start = order.read_u32(buffer, offset + 4) as usize;
+        buffer.get(start..start + length)
+            .ok_or(ConvertError::Truncated { offset: start, len: length, available: buffer.len() })
+    }
+
+    // 第 `index` 个条目的原文字节 (msgctxt EOT msgid [NUL msgid_plural])
+    fn original_bytes(&self, index: usize) -> Result<&[u8], ConvertError> {
+        self.string_bytes(self.header.original_table_offset, index)
+    }
+
+    // 第 `index` 个条目的译文字节，复数译文以NUL分隔
+    fn translation_bytes(&self, index: usize) -> Result<&[u8], ConvertError> {
+        self.string_bytes(self.header.translation_table_offset, index)
+    }
+
+    fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, ConvertError> {
+        charset::decode(bytes, self.encoding)
+    }
+
+    /// 读取第 `index` 个条目，解码失败时错误位置为该条目
+    pub fn entry(&self, index: usize) -> Result<MoEntry<'_>, ConvertError> {
+        let original = self.decode(self.original_bytes(index)?)
+            .map_err(|e| e.at(Location::Entry(index)))?;
+        let translation = self.decode(self.translation_bytes(index)?)
+            .map_err(|e| e.at(Location::Entry(index)))?;
+
+        // msgctxt 与 msgid 以 EOT 分隔；复数条目的 msgid 和 msgid_plural、各个复数译文均以 NUL 分隔
+        let (msgctxt, original) = match split_once(original, '\x04') {
+            (ctx, Some(text)) => (Some(ctx), text),
+            (text, None) => (None, text),
+        };
+        let (msgid, msgid_plural) = split_once(original, '\0');
+        let msgstr = if msgid_plural.is_some() {
+            split(translation, '\0')
+        } else {
+            vec![translation]
+        };
+
+        Ok(MoEntry { msgctxt, msgid, msgid_plural, msgstr })
+    }
+
+    /// 按文件中的顺序逐个读取条目
+    pub fn entries(&self) -> impl Iterator<Item = Result<MoEntry<'_>, ConvertError>> + '_ {
+        (0..self.len()).map(move |index| self.entry(index))
+    }
+
+    /// 头部条目的内容，没有头部条目时返回None
+    pub fn header_text(&self) -> Result<Option<Cow<'_, str>>, ConvertError> {
+        if self.is_empty() || !self.original_bytes(0)?.is_empty() {
+            return Ok(None);
+        }
+        self.decode(self.translation_bytes(0)?).map(Some)
+    }
+
+    /// 查找 msgctxt + msgid 对应的条目序号
+    ///
+    /// 有哈希表时按哈希表查找，否则在按原文排序的字符串表中二分查找
+    pub fn find(&self, msgctxt: Option<&str>, msgid: &str) -> Option<usize> {
+        let key = match msgctxt {
+            Some(ctx) => format!("{}\u{4}{}", ctx, msgid),
+            None => msgid.to_string(),
+        };
+        let (key, _, unmappable) = self.encoding.encode(&key);
+        if unmappable {
+            return None;
+        }
+
+        // 复数条目的原文还带有 NUL 和 msgid_plural，只比较NUL之前的部分
+        let singular = |index: usize| -> Option<&[u8]> {
+            let bytes = self.original_bytes(index).ok()?;
+            Some(bytes.split(|&b| b == 0).next().unwrap_or(bytes))
+        };
+
+        let size = self.header.hash_table_size;
+        if size >= 3 {
+            let (mut slot_index, increment) = mo_converter::hash_probe(mo_converter::hash_string(&key), size);
+            for _ in 0..size {
+                let value = self.hash_slot(slot_index)?;
+                if value == 0 {
+                    return None;
+                }
+                let index = value as usize - 1;
+                if singular(index)? == key.as_ref() {
+                    return Some(index);
+                }
+                slot_index = mo_converter::hash_next(slot_index, increment, size);
+            }
+            return None;
+        }
+
+        // NUL小于其他所有字节，按完整原文排序与按单数原文排序一致
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            match singular(mid)?.cmp(key.as_ref()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    fn hash_slot(&self, index: u32) -> Option<u32> {
+        let offset = self.header.hash_table_offset as usize + index as usize * 4;
+        if offset + 4 > self.bytes().len() {
+            return None;
+        }
+        Some(self.header.byte_order.read_u32(self.bytes(), offset))
+    }
+
+    /// 检查哈希表的一致性
+    ///
+    /// # Returns
+    ///
+    /// 文件不含哈希表时返回Ok(false)；哈希表中每个原文都能被查找到自身时返回Ok(true)，
+    /// 否则返回描述第一个问题的Err
+    pub fn verify_hash_table(&self) -> Result<bool, ConvertError> {
+        let size = self.header.hash_table_size;
+        if size == 0 {
+            return Ok(false);
+        }
+        if size < 3 {
+            return Err(ConvertError::format(Location::Offset(20), format!("哈希表大小无效: {}", size)));
+        }
+        let hash_table_offset = self.header.hash_table_offset as usize;
+        if hash_table_offset + size as usize * 4 > self.bytes().len() {
+            return Err(ConvertError::Truncated { offset: hash_table_offset, len: size as usize * 4, available: self.bytes().len() });
+        }
+
+        let num_strings = self.header.num_strings;
+        for i in 0..self.len() {
+            let key = self.original_bytes(i)?;
+
+            // 按运行时的查找方式探测，最多探测 size 次
+            let (mut index, increment) = mo_converter::hash_probe(mo_converter::hash_string(key), size);
+            let mut found = false;
+            for _ in 0..size {
+                match self.hash_slot(index).unwrap_or(0) {
+                    0 => break,
+                    value if value as usize - 1 == i => {
+                        found = true;
+                        break;
+                    }
+                    value if value > num_strings => {
+                        return Err(ConvertError::format(
+                            Location::Offset(hash_table_offset + index as usize * 4),
+                            format!("哈希表第 {} 项指向不存在的条目 {}", index, value - 1),
+                        ));
+                    }
+                    _ => index = mo_converter::hash_next(index, increment, size),
+                }
+            }
+
+            if !found {
+                return Err(ConvertError::format(
+                    Location::Entry(i),
+                    format!("哈希表不一致: 条目 \"{}\" 无法通过哈希表找到", String::from_utf8_lossy(key).replace('\0', "|")),
+                ));
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+// 在第一个分隔符处拆分，借用的字符串拆分后仍然借用
+fn split_once(text: Cow<'_, str>, separator: char) -> (Cow<'_, str>, Option<Cow<'_, str>>) {
+    match text {
+        Cow::Borrowed(s) => match s.split_once(separator) {
+            Some((head, tail)) => (Cow::Borrowed(head), Some(Cow::Borrowed(tail))),
+            None => (Cow::Borrowed(s), None),
+        },
+        Cow::Owned(s) => match s.split_once(separator) {
+            Some((head, tail)) => (Cow::Owned(head.to_string()), Some(Cow::Owned(tail.to_string()))),
+            None => (Cow::Owned(s), None),
+        },
+    }
+}
+
+// 按分隔符拆分为多段
+fn split(text: Cow<'_, str>, separator: char) -> Vec<Cow<'_, str>> {
+    match text {
+        Cow::Borrowed(s) => s.split(separator).map(Cow::Borrowed).collect(),
+        Cow::Owned(s) => s.split(separator).map(|part| Cow::Owned(part.to_string())).collect(),
+    }
+}
@@ -1,4 +1,21 @@
 pub mod mo_converter;
+pub mod mo_file;
 pub mod po_converter;
 pub mod po_merger;
-pub mod csv_converter; 
\ No newline at end of file
+pub mod csv_converter;
+pub mod catalog;
+pub mod charset;
+pub mod mo_catalog;
+pub mod roundtrip;
+pub mod three_way;
+pub mod plural;
+pub mod po_parser;
+pub mod po_validator;
+pub mod po_writer;
+pub mod po_updater;
+pub mod pot_template;
+pub mod pipeline;
+pub mod progress;
+pub mod merge_cache;
+pub mod error;
+pub mod header;
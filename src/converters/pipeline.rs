@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use super::error::ConvertError;
+use super::merge_cache::{MergeCache, MergeOutput};
+use super::po_converter::{CompileOptions, CompileReport, PoConverter};
+use super::po_merger::MergeOptions;
+use super::po_parser::Diagnostic;
+use super::po_validator;
+use super::po_writer;
+use super::progress::{self, MergeControl};
+
+/// 应用语言包的选项
+#[derive(Clone, Debug, Default)]
+pub struct ApplyOptions {
+    pub merge: MergeOptions,               // 合并选项
+    pub compile: CompileOptions,           // MO编译选项
+    pub intermediate_dir: Option<PathBuf>, // 设置时在该目录写出中间PO文件，便于排查问题
+    pub reject_invalid: bool,              // 有语言包未通过校验时不生成输出
+}
+
+/// 应用语言包的结果
+#[derive(Clone, Debug, Default)]
+pub struct ApplyReport {
+    pub diagnostics: Vec<Diagnostic>, // 宽松模式下跳过的问题
+    pub validation: Vec<Diagnostic>,  // 语言包的校验结果
+    pub compile: CompileReport,       // 编译时写入和跳过的条目数
+}
+
+/// 将语言包应用到主MO文件
+///
+/// 主MO文件和语言包通过 `cache` 读取，内容没有变化的文件不会重新解析，
+/// 在内存中合并后直接编译为MO文件，不经过中间PO文件。语言包按顺序合并(第一个优先级最高)，
+/// 主MO文件优先级最低，设置了 `ignore_main_entries` 时则最高且其中的条目不会被替换。
+/// 输出的头部以主MO文件的头部为基础生成。语言包会先校验，设置了 `reject_invalid` 时
+/// 有错误则返回 [`ConvertError::Rejected`] 而不生成输出。输出先写入临时文件，取消或失败时不会留下写了一半的MO文件
+///
+/// # 参数
+/// * `cache` - 合并缓存，多次应用之间复用
+/// * `base_mo` - 主MO文件路径
+/// * `mod_files` - 语言包PO文件路径列表,按优先级排序(第一个最高)
+/// * `output_mo` - 输出MO文件路径
+/// * `options` - 应用选项
+/// * `control` - 进度回调和取消标志
+///
+/// # 返回
+/// * `Result<ApplyReport, ConvertError>` - 成功时返回解析问题、校验结果和编译统计，失败时返回错误
+pub fn apply_mods_to_mo(
+    cache: &mut MergeCache,
+    base_mo: &Path,
+    mod_files: &[PathBuf],
+    output_mo: &Path,
+    options: &ApplyOptions,
+    control: &MergeControl,
+) -> Result<ApplyReport, ConvertError> {
+    let MergeOutput { catalog: merged, diagnostics, validation } = cache.merge(base_mo, mod_files, &options.merge, control)?;
+
+    if options.reject_invalid && po_validator::has_errors(&validation) {
+        return Err(ConvertError::Rejected(validation.into_iter().filter(Diagnostic::is_error).collect()));
+    }
+
+    if let Some(dir) = &options.intermediate_dir {
+        // 主MO文件在合并时已读入缓存，不再重新读取
+        if let Some(main) = cache.catalog(base_mo) {
+            po_writer::write_po_file(&main, dir.join("main.po"), &options.merge.write)?;
+        }
+        po_writer::write_po_file(&merged, dir.join("final_merged.po"), &options.merge.write)?;
+    }
+
+    // 直接编译为MO文件
+    let mut compile = CompileReport::default();
+    progress::write_output(output_mo, control, |writer| {
+        compile = PoConverter::write_mo_file(writer, &merged, &options.compile)?;
+        Ok(())
+    })?;
+
+    Ok(ApplyReport { diagnostics, validation, compile })
+}
@@ -0,0 +1,348 @@
+use super::error::ConvertError;
+
+/// `Plural-Forms` 头部字段: 复数形式数量和用于选择复数形式的C表达式
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluralForms {
+    pub nplurals: usize,
+    expr: Expr,
+}
+
+impl Default for PluralForms {
+    /// 没有 `Plural-Forms` 时 gettext 使用的默认规则: nplurals=2; plural=(n != 1);
+    fn default() -> Self {
+        Self {
+            nplurals: 2,
+            expr: Expr::Binary(BinaryOp::Ne, Box::new(Expr::N), Box::new(Expr::Num(1))),
+        }
+    }
+}
+
+impl PluralForms {
+    /// 解析 `Plural-Forms` 字段的值，例如 `nplurals=2; plural=(n != 1);`
+    pub fn parse(value: &str) -> Result<Self, ConvertError> {
+        Self::parse_fields(value).map_err(ConvertError::PluralForms)
+    }
+
+    // 解析各字段，错误信息不含前缀
+    fn parse_fields(value: &str) -> Result<Self, String> {
+        let mut nplurals = None;
+        let mut plural = None;
+        for part in value.split(';') {
+            if let Some((key, val)) = part.split_once('=') {
+                match key.trim() {
+                    "nplurals" => {
+                        nplurals = Some(val.trim().parse::<usize>()
+                            .map_err(|_| format!("无效的nplurals: {}", val.trim()))?);
+                    }
+                    "plural" => plural = Some(val.trim()),
+                    _ => {}
+                }
+            }
+        }
+
+        let nplurals = nplurals.ok_or_else(|| "Plural-Forms 缺少 nplurals".to_string())?;
+        if nplurals == 0 {
+            return Err("nplurals 必须大于0".to_string());
+        }
+        let plural = plural.ok_or_else(|| "Plural-Forms 缺少 plural 表达式".to_string())?;
+
+        Ok(Self {
+            nplurals,
+            expr: Parser::new(plural)?.parse()?,
+        })
+    }
+
+    /// 数量 `n` 对应的复数形式序号，超出 nplurals 时与 gettext 一样回退到0
+    pub fn index(&self, n: u64) -> usize {
+        let index = self.expr.eval(n) as usize;
+        if index < self.nplurals { index } else { 0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    N,
+    Num(u64),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    // 按 gettext 的语义使用无符号整数求值，除以0时结果为0
+    fn eval(&self, n: u64) -> u64 {
+        match self {
+            Expr::N => n,
+            Expr::Num(value) => *value,
+            Expr::Not(inner) => (inner.eval(n) == 0) as u64,
+            Expr::Ternary(cond, then, otherwise) => {
+                if cond.eval(n) != 0 { then.eval(n) } else { otherwise.eval(n) }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let l = lhs.eval(n);
+                // || 和 && 短路求值
+                match op {
+                    BinaryOp::Or => return (l != 0 || rhs.eval(n) != 0) as u64,
+                    BinaryOp::And => return (l != 0 && rhs.eval(n) != 0) as u64,
+                    _ => {}
+                }
+                let r = rhs.eval(n);
+                match op {
+                    BinaryOp::Eq => (l == r) as u64,
+                    BinaryOp::Ne => (l != r) as u64,
+                    BinaryOp::Lt => (l < r) as u64,
+                    BinaryOp::Gt => (l > r) as u64,
+                    BinaryOp::Le => (l <= r) as u64,
+                    BinaryOp::Ge => (l >= r) as u64,
+                    BinaryOp::Add => l.wrapping_add(r),
+                    BinaryOp::Sub => l.wrapping_sub(r),
+                    BinaryOp::Mul => l.wrapping_mul(r),
+                    BinaryOp::Div => l.checked_div(r).unwrap_or(0),
+                    BinaryOp::Rem => l.checked_rem(r).unwrap_or(0),
+                    BinaryOp::Or | BinaryOp::And => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    N,
+    Num(u64),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Question,
+    Colon,
+}
+
+// 运算符按长度从长到短排列，保证 "<=" 优先于 "<" 匹配
+const OPERATORS: [&str; 15] = ["||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "?"];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+        } else if c.is_ascii_digit() {
+            let start = pos;
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            let value = input[start..pos].parse()
+                .map_err(|_| format!("复数表达式中的数字过大: {}", &input[start..pos]))?;
+            tokens.push(Token::Num(value));
+        } else if c == b'n' {
+            tokens.push(Token::N);
+            pos += 1;
+        } else if c == b'(' {
+            tokens.push(Token::LParen);
+            pos += 1;
+        } else if c == b')' {
+            tokens.push(Token::RParen);
+            pos += 1;
+        } else if c == b':' {
+            tokens.push(Token::Colon);
+            pos += 1;
+        } else if let Some(op) = OPERATORS.iter().find(|op| input[pos..].starts_with(**op)) {
+            tokens.push(if *op == "?" { Token::Question } else { Token::Op(op) });
+            pos += op.len();
+        } else {
+            return Err(format!("复数表达式中有无法识别的字符 '{}' (位置 {})", &input[pos..].chars().next().unwrap_or(' '), pos));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// 递归下降解析器，优先级从低到高: ?: || && ==/!= 比较 +/- 乘除 !
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(input: &str) -> Result<Self, String> {
+        Ok(Self { tokens: tokenize(input)?, pos: 0 })
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.ternary()?;
+        if self.pos != self.tokens.len() {
+            return Err(format!("复数表达式在第 {} 个记号处有多余内容", self.pos + 1));
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), String> {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("复数表达式缺少 '{}'", what))
+        }
+    }
+
+    fn ternary(&mut self) -> Result<Expr, String> {
+        let cond = self.binary(0)?;
+        if self.peek() != Some(Token::Question) {
+            return Ok(cond);
+        }
+        self.pos += 1;
+        let then = self.ternary()?;
+        self.expect(Token::Colon, ":")?;
+        let otherwise = self.ternary()?;
+        Ok(Expr::Ternary(Box::new(cond), Box::new(then), Box::new(otherwise)))
+    }
+
+    // 按优先级层级解析左结合的二元运算
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[(&str, BinaryOp)]; 6] = [
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+            &[("<=", BinaryOp::Le), (">=", BinaryOp::Ge), ("<", BinaryOp::Lt), (">", BinaryOp::Gt)],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(Token::Op(symbol)) = self.peek() {
+            let Some((_, op)) = LEVELS[level].iter().find(|(s, _)| *s == symbol) else {
+                break;
+            };
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::N) => {
+                self.pos += 1;
+                Ok(Expr::N)
+            }
+            Some(Token::Num(value)) => {
+                self.pos += 1;
+                Ok(Expr::Num(value))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.ternary()?;
+                self.expect(Token::RParen, ")")?;
+                Ok(expr)
+            }
+            Some(_) => Err(format!("复数表达式在第 {} 个记号处语法错误", self.pos + 1)),
+            None => Err("复数表达式意外结束".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indices(plural_forms: &str, numbers: &[u64]) -> Vec<usize> {
+        let forms = PluralForms::parse(plural_forms).unwrap();
+        numbers.iter().map(|&n| forms.index(n)).collect()
+    }
+
+    #[test]
+    fn default_matches_english() {
+        let forms = PluralForms::default();
+        assert_eq!(forms, PluralForms::parse("nplurals=2; plural=(n != 1);").unwrap());
+        assert_eq!([0, 1, 2, 100].map(|n| forms.index(n)), [1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn common_languages() {
+        // 日语、中文
+        assert_eq!(indices("nplurals=1; plural=0;", &[0, 1, 2]), [0, 0, 0]);
+        // 法语
+        assert_eq!(indices("nplurals=2; plural=(n > 1);", &[0, 1, 2]), [0, 0, 1]);
+        // 俄语
+        let russian = "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);";
+        assert_eq!(indices(russian, &[1, 2, 5, 11, 12, 21, 22, 25, 111, 101]), [0, 1, 2, 2, 2, 0, 1, 2, 2, 0]);
+        // 捷克语，不带括号
+        let czech = "nplurals=3; plural=(n==1) ? 0 : (n>=2 && n<=4) ? 1 : 2;";
+        assert_eq!(indices(czech, &[1, 2, 4, 5, 0]), [0, 1, 1, 2, 2]);
+        // 阿拉伯语
+        let arabic = "nplurals=6; plural=n==0 ? 0 : n==1 ? 1 : n==2 ? 2 : n%100>=3 && n%100<=10 ? 3 : n%100>=11 ? 4 : 5;";
+        assert_eq!(indices(arabic, &[0, 1, 2, 3, 10, 11, 99, 100, 102]), [0, 1, 2, 3, 3, 4, 4, 5, 5]);
+    }
+
+    #[test]
+    fn operator_precedence_and_associativity() {
+        // 1 + 2*3 == 7 为真，取1
+        assert_eq!(indices("nplurals=2; plural=1 + 2 * 3 == 7;", &[0]), [1]);
+        // 减法左结合: 10 - 4 - 3 = 3
+        assert_eq!(indices("nplurals=4; plural=10 - 4 - 3;", &[0]), [3]);
+        // ! 优先于比较
+        assert_eq!(indices("nplurals=2; plural=!n == 0;", &[0, 5]), [0, 1]);
+        // 嵌套的 ?: 右结合
+        assert_eq!(indices("nplurals=3; plural=n ? n == 1 ? 1 : 2 : 0;", &[0, 1, 2]), [0, 1, 2]);
+    }
+
+    #[test]
+    fn unsigned_arithmetic_like_gettext() {
+        // 除以0时结果为0
+        assert_eq!(indices("nplurals=2; plural=1 / (n - n) + n % 0;", &[3]), [0]);
+        // 无符号减法回绕后不小于0
+        assert_eq!(indices("nplurals=2; plural=n - 1 > 5;", &[0]), [1]);
+        // 超出 nplurals 的序号回退到0
+        assert_eq!(indices("nplurals=2; plural=n;", &[0, 1, 2, 7]), [0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn rejects_invalid_plural_forms() {
+        for value in [
+            "plural=(n != 1);",
+            "nplurals=0; plural=0;",
+            "nplurals=x; plural=0;",
+            "nplurals=2;",
+            "nplurals=2; plural=(n != 1;",
+            "nplurals=2; plural=n != 1);",
+            "nplurals=2; plural=n ? 1;",
+            "nplurals=2; plural=n $ 1;",
+            "nplurals=2; plural=;",
+        ] {
+            assert!(matches!(PluralForms::parse(value), Err(ConvertError::PluralForms(_))), "{}", value);
+        }
+    }
+}
@@ -1,9 +1,75 @@
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::Write;
 use std::path::Path;
-use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
+
+use super::catalog::{Catalog, Entry, MessageKey};
+use super::error::ConvertError;
+use super::header::Header;
+use super::mo_converter::{self, ByteOrder, MO_HEADER_SIZE, MO_MAGIC};
+use super::po_merger::{self, DuplicatePolicy};
+use super::progress::{self, MergeControl};
+
+/// MO编译选项，默认与 msgfmt 一样跳过模糊条目和未翻译的条目
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+    pub byte_order: ByteOrder,              // 输出MO文件的字节序
+    pub include_fuzzy: bool,                // 是否编译模糊条目
+    pub include_untranslated: bool,         // 是否编译未翻译的条目
+    pub identical_as_untranslated: bool,    // 译文与原文相同的条目视为未翻译
+    pub duplicate_policy: DuplicatePolicy,  // 输入PO文件中重复条目的处理方式
+}
+
+/// MO编译结果: 写入的条目数和按原因统计的跳过条目数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompileReport {
+    pub compiled: usize,     // 写入的条目数(不含头部)
+    pub fuzzy: usize,        // 跳过的模糊条目
+    pub untranslated: usize, // 跳过的未翻译条目
+    pub identical: usize,    // 因译文与原文相同而跳过的条目
+}
+
+impl CompileReport {
+    /// 跳过的条目总数
+    pub fn skipped(&self) -> usize {
+        self.fuzzy + self.untranslated + self.identical
+    }
+}
+
+// 条目被跳过的原因
+enum Skip {
+    Fuzzy,
+    Untranslated,
+    Identical,
+}
+
+impl CompileOptions {
+    /// 条目是否会写入MO文件
+    pub fn compiles(&self, entry: &Entry) -> bool {
+        !entry.obsolete && self.skip_reason(entry).is_none()
+    }
+    
+    // 判断条目是否应跳过，模糊优先于未翻译
+    fn skip_reason(&self, entry: &Entry) -> Option<Skip> {
+        if !self.include_fuzzy && entry.is_fuzzy() {
+            Some(Skip::Fuzzy)
+        } else if self.include_untranslated {
+            None
+        } else if !entry.is_translated() {
+            Some(Skip::Untranslated)
+        } else if self.identical_as_untranslated && is_identical(entry) {
+            Some(Skip::Identical)
+        } else {
+            None
+        }
+    }
+}
+
+/// 译文是否与原文相同: 每个译文都等于 msgid，复数条目的其余译文也可以等于 msgid_plural
+pub fn is_identical(entry: &Entry) -> bool {
+    entry.msgstr.iter().enumerate().all(|(index, msgstr)| {
+        *msgstr == entry.msgid || (index > 0 && entry.msgid_plural.as_deref() == Some(msgstr.as_str()))
+    })
+}
 
 pub struct PoConverter;
 
@@ -17,212 +83,90 @@ impl PoConverter {
     /// 
     /// # Returns
     /// 
-    /// 成功返回Ok(()), 失败返回带错误信息的Err
-    pub fn convert_po_to_mo(input: &Path, output: &Path) -> Result<(), String> {
+    /// 成功返回编译结果, 失败返回错误
+    pub fn convert_po_to_mo(input: &Path, output: &Path) -> Result<CompileReport, ConvertError> {
+        Self::convert_po_to_mo_with(input, output, &CompileOptions::default())
+    }
+    
+    /// 按指定编译选项将PO文件转换为MO文件，重复条目按 `options.duplicate_policy` 处理
+    ///
+    /// 输出先写入临时文件，失败时不会留下写了一半的文件
+    pub fn convert_po_to_mo_with(input: &Path, output: &Path, options: &CompileOptions) -> Result<CompileReport, ConvertError> {
         // 解析PO文件，获取所有翻译条目
-        let entries = Self::parse_po_file(input)?;
-        
-        // 排序条目 (原始文本)，考虑 msgctxt
-        let mut sorted_entries: Vec<_> = entries.values().collect();
-        sorted_entries.sort_by(|a, b| {
-            // 先按 msgctxt 排序，再按 msgid 排序
-            match (&a.msgctxt, &b.msgctxt) {
-                (Some(a_ctx), Some(b_ctx)) => a_ctx.cmp(b_ctx).then(a.msgid.cmp(&b.msgid)),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.msgid.cmp(&b.msgid),
-            }
-        });
-        
-        // 创建输出文件
-        let mut file = File::create(output).map_err(|e| format!("无法创建输出文件: {}", e))?;
+        let mut catalog = po_merger::parse_po_file(input)?;
+        catalog.entries = po_merger::apply_duplicate_policy(catalog.entries, options.duplicate_policy)?;
         
         // 构建MO文件
-        Self::write_mo_file(&mut file, sorted_entries)?;
-        
-        Ok(())
+        let mut report = CompileReport::default();
+        progress::write_output(output, &MergeControl::default(), |writer| {
+            report = Self::write_mo_file(writer, &catalog, options)?;
+            Ok(())
+        })?;
+        Ok(report)
     }
     
-    /// 解析PO文件内容，提取所有翻译条目
-    fn parse_po_file(input: &Path) -> Result<HashMap<String, PoEntry>, String> {
-        let file = File::open(input).map_err(|e| format!("无法打开输入文件: {}", e))?;
-        let reader = BufReader::new(file);
-        let lines: Vec<String> = reader.lines().collect::<Result<Vec<_>, io::Error>>()
-            .map_err(|e| format!("读取PO文件时出错: {}", e))?;
-        
-        let mut entries = HashMap::new();
-        let mut current_entry = PoEntry::default();
-        let mut reading_msgctxt = false;
-        let mut reading_msgid = false;
-        let mut reading_msgstr = false;
+    /// 写入MO文件
+    /// 
+    /// 头部写为空msgid条目；过时条目不写入，模糊和未翻译的条目按 `options` 跳过，
+    /// 重复的 msgctxt+msgid 只保留最后一个，
+    /// 所有条目按MO原文键的字节顺序排序，以便运行时二分查找；同时写入gettext哈希表，
+    /// 供运行时以O(1)查找。所有整数按 `options.byte_order` 指定的字节序写入
+    pub fn write_mo_file<W: Write>(writer: &mut W, catalog: &Catalog, options: &CompileOptions) -> Result<CompileReport, ConvertError> {
+        let order = options.byte_order;
         
-        for line in lines {
-            let line = line.trim();
-            
-            if line.is_empty() || line.starts_with('#') {
-                if !current_entry.msgid.is_empty() {
-                    let key = match &current_entry.msgctxt {
-                        Some(ctx) if !ctx.is_empty() => format!("{}\u{4}{}", ctx, current_entry.msgid),
-                        _ => current_entry.msgid.clone()
-                    };
-                    entries.insert(key, current_entry.clone());
-                    current_entry = PoEntry::default();
+        // 收集所有条目 (msgctxt+msgid -> (MO原文键, 译文))，确保有PO头部信息；
+        // 按 msgctxt+msgid 去重，复数原文不同的重复条目也只保留最后一个
+        let header = if catalog.header.is_empty() { Header::minimal() } else { catalog.header.clone() };
+        let nplurals = catalog.nplurals();
+        let mut messages: HashMap<MessageKey, (String, String)> = HashMap::with_capacity(catalog.len() + 1);
+        messages.insert((None, String::new()), (String::new(), header.to_string()));
+        let mut report = CompileReport::default();
+        for entry in catalog.entries.iter().filter(|e| !e.obsolete) {
+            if let Some(reason) = options.skip_reason(entry) {
+                match reason {
+                    Skip::Fuzzy => report.fuzzy += 1,
+                    Skip::Untranslated => report.untranslated += 1,
+                    Skip::Identical => report.identical += 1,
                 }
-                reading_msgctxt = false;
-                reading_msgid = false;
-                reading_msgstr = false;
                 continue;
             }
             
-            if line.starts_with("msgctxt ") {
-                if !current_entry.msgid.is_empty() {
-                    let key = match &current_entry.msgctxt {
-                        Some(ctx) if !ctx.is_empty() => format!("{}\u{4}{}", ctx, current_entry.msgid),
-                        _ => current_entry.msgid.clone()
-                    };
-                    entries.insert(key, current_entry.clone());
-                    current_entry = PoEntry::default();
-                }
-                reading_msgctxt = true;
-                reading_msgid = false;
-                reading_msgstr = false;
-                let content = Self::extract_content(line, "msgctxt ");
-                current_entry.msgctxt = Some(content);
-            } else if line.starts_with("msgid ") {
-                reading_msgctxt = false;
-                reading_msgid = true;
-                reading_msgstr = false;
-                let content = Self::extract_content(line, "msgid ");
-                current_entry.msgid = content;
-            } else if line.starts_with("msgstr ") {
-                reading_msgctxt = false;
-                reading_msgid = false;
-                reading_msgstr = true;
-                let content = Self::extract_content(line, "msgstr ");
-                current_entry.msgstr = content;
-            } else if line.starts_with("\"") && line.ends_with("\"") {
-                let content = Self::extract_string_line(line);
-                if reading_msgctxt {
-                    current_entry.msgctxt.as_mut().unwrap().push_str(&content);
-                } else if reading_msgid {
-                    current_entry.msgid.push_str(&content);
-                } else if reading_msgstr {
-                    current_entry.msgstr.push_str(&content);
-                }
-            }
-        }
-        
-        // 处理最后一个条目
-        if !current_entry.msgid.is_empty() {
-            let key = match &current_entry.msgctxt {
-                Some(ctx) if !ctx.is_empty() => format!("{}\u{4}{}", ctx, current_entry.msgid),
-                _ => current_entry.msgid.clone()
+            // 复数条目的译文数量补齐到 nplurals，避免运行时按复数索引取不到译文
+            let msgstr = if entry.msgid_plural.is_some() && entry.msgstr.len() < nplurals {
+                let mut padded = entry.clone();
+                padded.msgstr.resize(nplurals, String::new());
+                padded.mo_msgstr()
+            } else {
+                entry.mo_msgstr()
             };
-            entries.insert(key, current_entry);
-        }
-        
-        // 确保有PO头部信息
-        if !entries.contains_key("") {
-            let mut result_with_header = HashMap::new();
-            result_with_header.insert(String::new(), PoEntry {
-                msgctxt: None,
-                msgid: String::new(),
-                msgstr: "Content-Type: text/plain; charset=UTF-8\nContent-Transfer-Encoding: 8bit\n".to_string(),
-            });
-            
-            for (k, v) in entries {
-                result_with_header.insert(k, v);
-            }
-            
-            Ok(result_with_header)
-        } else {
-            Ok(entries)
+            messages.insert(entry.key(), (entry.mo_msgid(), msgstr));
         }
-    }
-    
-    /// 从行中提取内容
-    fn extract_content(line: &str, prefix: &str) -> String {
-        let content = line.trim_start_matches(prefix).trim();
-        if content.starts_with('"') && content.ends_with('"') && content.len() >= 2 {
-            Self::unescape_po_string(&content[1..content.len()-1])
-        } else {
-            String::new()
-        }
-    }
-    
-    /// 从字符串行提取内容
-    fn extract_string_line(line: &str) -> String {
-        if line.starts_with('"') && line.ends_with('"') && line.len() >= 2 {
-            Self::unescape_po_string(&line[1..line.len()-1])
-        } else {
-            String::new()
-        }
-    }
-    
-    /// 反转义PO文件中的字符串
-    fn unescape_po_string(s: &str) -> String {
-        let mut result = String::with_capacity(s.len());
-        let mut chars = s.chars().peekable();
         
-        while let Some(c) = chars.next() {
-            if c == '\\' && chars.peek().is_some() {
-                match chars.next().unwrap() {
-                    'n' => result.push('\n'),
-                    'r' => result.push('\r'),
-                    't' => result.push('\t'),
-                    '\\' => result.push('\\'),
-                    '"' => result.push('"'),
-                    c => {
-                        result.push('\\');
-                        result.push(c);
-                    }
-                }
-            } else {
-                result.push(c);
-            }
-        }
+        report.compiled = messages.len() - 1;
         
-        result
-    }
-    
-    /// 写入MO文件
-    fn write_mo_file<W: Write>(writer: &mut W, entries: Vec<&PoEntry>) -> Result<(), String> {
-        // MO文件格式常量
-        const MAGIC_NUMBER: u32 = 0x9504_12DE; // Little endian
-        const MO_HEADER_SIZE: u32 = 28;
+        // 排序条目 (原始文本)，头部的空字符串排在最前面
+        let mut sorted_entries: Vec<_> = messages.into_values().collect();
+        sorted_entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
         
         // 计算表的大小和位置
-        let num_strings = entries.len() as u32;
-        let original_table_offset = MO_HEADER_SIZE;
+        let num_strings = sorted_entries.len() as u32;
+        let original_table_offset = MO_HEADER_SIZE as u32;
         let translation_table_offset = original_table_offset + num_strings * 8;
         
+        // 哈希表位于两个偏移表之后、字符串数据之前
+        let hash_table_size = mo_converter::hash_table_size(sorted_entries.len());
+        let hash_table_offset = translation_table_offset + num_strings * 8;
+        let hash_table = Self::build_hash_table(&sorted_entries, hash_table_size);
+        
         // 预先计算字符串偏移
-        let string_start_offset = translation_table_offset + num_strings * 8;
+        let string_start_offset = hash_table_offset + hash_table_size * 4;
         
         // 预先计算所有字符串在文件中的位置
-        let mut string_offsets = Vec::with_capacity(entries.len() * 2);
+        let mut string_offsets = Vec::with_capacity(sorted_entries.len() * 2);
         let mut current_offset = string_start_offset;
         let mut string_data = Vec::new();
         
-        // 首先确保空字符串(头信息)在最前面
-        let mut sorted_entries = entries;
-        sorted_entries.sort_by(|a, b| {
-            if a.msgid.is_empty() {
-                std::cmp::Ordering::Less
-            } else if b.msgid.is_empty() {
-                std::cmp::Ordering::Greater
-            } else {
-                a.msgid.cmp(&b.msgid)
-            }
-        });
-        
-        for entry in &sorted_entries {
-            // 如果有 msgctxt，将其与 msgid 组合
-            let msgid = match &entry.msgctxt {
-                Some(ctx) if !ctx.is_empty() => format!("{}\u{4}{}", ctx, entry.msgid),
-                _ => entry.msgid.clone()
-            };
-            
+        for (msgid, msgstr) in &sorted_entries {
             // 原始文本: msgid (可能包含 msgctxt)
             let msgid_bytes = msgid.as_bytes();
             string_offsets.push((msgid_bytes.len() as u32, current_offset));
@@ -231,7 +175,7 @@ impl PoConverter {
             current_offset += msgid_bytes.len() as u32 + 1;
             
             // 翻译文本: msgstr
-            let msgstr_bytes = entry.msgstr.as_bytes();
+            let msgstr_bytes = msgstr.as_bytes();
             string_offsets.push((msgstr_bytes.len() as u32, current_offset));
             string_data.extend_from_slice(msgstr_bytes);
             string_data.push(0); // Null terminator
@@ -239,40 +183,53 @@ impl PoConverter {
         }
         
         // 写入MO文件头
-        writer.write_all(&MAGIC_NUMBER.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?;
-        writer.write_all(&0u32.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?; // File format revision
-        writer.write_all(&num_strings.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?;
-        writer.write_all(&original_table_offset.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?;
-        writer.write_all(&translation_table_offset.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?;
-        writer.write_all(&0u32.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?; // Size of hashing table
-        writer.write_all(&0u32.to_le_bytes()).map_err(|e| format!("写入MO文件头失败: {}", e))?; // Offset of hashing table
+        writer.write_all(&order.u32_bytes(MO_MAGIC)).map_err(ConvertError::write)?;
+        writer.write_all(&order.u32_bytes(0u32)).map_err(ConvertError::write)?; // File format revision
+        writer.write_all(&order.u32_bytes(num_strings)).map_err(ConvertError::write)?;
+        writer.write_all(&order.u32_bytes(original_table_offset)).map_err(ConvertError::write)?;
+        writer.write_all(&order.u32_bytes(translation_table_offset)).map_err(ConvertError::write)?;
+        writer.write_all(&order.u32_bytes(hash_table_size)).map_err(ConvertError::write)?; // Size of hashing table
+        writer.write_all(&order.u32_bytes(hash_table_offset)).map_err(ConvertError::write)?; // Offset of hashing table
         
         // 写入原始文本表 (msgid 偏移表)
         for i in 0..num_strings as usize {
             let (length, offset) = string_offsets[i * 2];
-            writer.write_all(&length.to_le_bytes()).map_err(|e| format!("写入原始文本表失败: {}", e))?;
-            writer.write_all(&offset.to_le_bytes()).map_err(|e| format!("写入原始文本表失败: {}", e))?;
+            writer.write_all(&order.u32_bytes(length)).map_err(ConvertError::write)?;
+            writer.write_all(&order.u32_bytes(offset)).map_err(ConvertError::write)?;
         }
         
         // 写入翻译文本表 (msgstr 偏移表)
         for i in 0..num_strings as usize {
             let (length, offset) = string_offsets[i * 2 + 1];
-            writer.write_all(&length.to_le_bytes()).map_err(|e| format!("写入翻译文本表失败: {}", e))?;
-            writer.write_all(&offset.to_le_bytes()).map_err(|e| format!("写入翻译文本表失败: {}", e))?;
+            writer.write_all(&order.u32_bytes(length)).map_err(ConvertError::write)?;
+            writer.write_all(&order.u32_bytes(offset)).map_err(ConvertError::write)?;
+        }
+        
+        // 写入哈希表
+        for slot in &hash_table {
+            writer.write_all(&order.u32_bytes(*slot)).map_err(ConvertError::write)?;
         }
         
         // 写入所有字符串数据
-        writer.write_all(&string_data).map_err(|e| format!("写入字符串数据失败: {}", e))?;
+        writer.write_all(&string_data).map_err(ConvertError::write)?;
         
-        Ok(())
+        Ok(report)
+    }
+    
+    /// 构建gettext哈希表
+    /// 
+    /// 每个槽位存放条目序号加1，0表示空槽；冲突时按双重哈希的步长向后探测
+    fn build_hash_table(sorted_entries: &[(String, String)], size: u32) -> Vec<u32> {
+        let mut table = vec![0u32; size as usize];
+        for (i, (msgid, _)) in sorted_entries.iter().enumerate() {
+            let (mut index, increment) = mo_converter::hash_probe(mo_converter::hash_string(msgid.as_bytes()), size);
+            while table[index as usize] != 0 {
+                index = mo_converter::hash_next(index, increment, size);
+            }
+            table[index as usize] = i as u32 + 1;
+        }
+        table
     }
-}
-
-#[derive(Debug, Default, Clone)]
-struct PoEntry {
-    msgctxt: Option<String>,
-    msgid: String,
-    msgstr: String,
 }
 
 /// 命令行工具入口点
@@ -291,9 +248,152 @@ fn main() -> std::io::Result<()> {
     let output_path = Path::new(&args[2]);
     
     match PoConverter::convert_po_to_mo(input_path, output_path) {
-        Ok(()) => println!("转换完成: {}", output_path.display()),
+        Ok(report) => println!("转换完成: {} ({} 个条目)", output_path.display(), report.compiled),
         Err(e) => eprintln!("转换失败: {}", e),
     }
     
     Ok(())
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converters::mo_converter::MoConverter;
+    use crate::converters::mo_file::MoFile;
+
+    fn catalog(entries: Vec<Entry>) -> Catalog {
+        let mut catalog = Catalog::new();
+        catalog.header = Header::parse("Content-Type: text/plain; charset=UTF-8\nPlural-Forms: nplurals=2; plural=(n != 1);\n");
+        catalog.entries = entries;
+        catalog
+    }
+
+    fn plural(msgid: &str, msgid_plural: &str, msgstr: &[&str]) -> Entry {
+        Entry {
+            msgid: msgid.to_string(),
+            msgid_plural: Some(msgid_plural.to_string()),
+            msgstr: msgstr.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn compile(catalog: &Catalog, byte_order: ByteOrder) -> (Vec<u8>, CompileReport) {
+        let mut bytes = Vec::new();
+        let options = CompileOptions { byte_order, ..Default::default() };
+        let report = PoConverter::write_mo_file(&mut bytes, catalog, &options).unwrap();
+        (bytes, report)
+    }
+
+    // 与 GNU gettext 的 hash-string.c 相同，按 unsigned long 计算
+    fn gnu_hash(key: &[u8]) -> u64 {
+        let mut hval: u64 = 0;
+        for &c in key {
+            hval = (hval << 4) + c as u64;
+            let g = hval & (!0u64 << 28);
+            if g != 0 {
+                hval ^= g >> 24;
+                hval ^= g;
+            }
+        }
+        hval
+    }
+
+    // 按 GNU gettext dcigettext.c 的 _nl_find_msg 通过哈希表查找，返回译文
+    fn gnu_lookup(bytes: &[u8], order: ByteOrder, key: &str) -> Option<String> {
+        let word = |offset: usize| order.read_u32(bytes, offset) as usize;
+        let string = |table: usize, index: usize| {
+            let (length, offset) = (word(table + index * 8), word(table + index * 8 + 4));
+            &bytes[offset..offset + length]
+        };
+        let (originals, translations) = (word(12), word(16));
+        let (size, hash_table) = (word(20) as u64, word(24));
+
+        let hval = gnu_hash(key.as_bytes());
+        let mut index = hval % size;
+        let increment = 1 + hval % (size - 2);
+        loop {
+            let nstr = word(hash_table + index as usize * 4);
+            if nstr == 0 {
+                return None;
+            }
+            let original = string(originals, nstr - 1);
+            // 与 strcmp 一样只比较到第一个NUL，复数条目按单数原文匹配
+            if original.split(|&b| b == 0).next() == Some(key.as_bytes()) {
+                return Some(String::from_utf8(string(translations, nstr - 1).to_vec()).unwrap());
+            }
+            index = if index >= size - increment { index - (size - increment) } else { index + increment };
+        }
+    }
+
+    #[test]
+    fn hash_string_matches_gettext() {
+        assert_eq!(mo_converter::hash_string(b"Hello"), 5161775);
+        assert_eq!(mo_converter::hash_string(b"Open\x04File"), 199793221);
+        assert_eq!(mo_converter::hash_string(b"The quick brown fox"), 62553624);
+        assert_eq!(mo_converter::hash_string(b"file\0files"), mo_converter::hash_string(b"file"));
+    }
+
+    #[test]
+    fn hash_table_matches_gnu_lookup() {
+        let mut entries: Vec<Entry> = (0..200)
+            .map(|i| Entry::new(None, format!("Message {}", i), format!("消息 {}", i)))
+            .collect();
+        entries.push(Entry::new(Some("Operator".to_string()), "Open".to_string(), "打开".to_string()));
+        entries.push(Entry::new(Some("Action".to_string()), "Open".to_string(), "开启".to_string()));
+        entries.push(plural("%d file", "%d files", &["%d 个文件", "%d 个文件们"]));
+        let catalog = catalog(entries);
+
+        for order in [ByteOrder::Little, ByteOrder::Big] {
+            let (bytes, report) = compile(&catalog, order);
+            assert_eq!(report.compiled, 203);
+            assert_eq!(gnu_lookup(&bytes, order, "Message 0").as_deref(), Some("消息 0"));
+            assert_eq!(gnu_lookup(&bytes, order, "Message 199").as_deref(), Some("消息 199"));
+            assert_eq!(gnu_lookup(&bytes, order, "Operator\u{4}Open").as_deref(), Some("打开"));
+            assert_eq!(gnu_lookup(&bytes, order, "Action\u{4}Open").as_deref(), Some("开启"));
+            assert_eq!(gnu_lookup(&bytes, order, "%d file").as_deref(), Some("%d 个文件\0%d 个文件们"));
+            assert_eq!(gnu_lookup(&bytes, order, "Open"), None);
+            assert_eq!(gnu_lookup(&bytes, order, "Message 200"), None);
+
+            let mo = MoFile::from_bytes(bytes).unwrap();
+            assert!(mo.verify_hash_table().unwrap());
+            assert_eq!(mo.find(Some("Action"), "Open").map(|i| mo.entry(i).unwrap().msgstr[0].to_string()).as_deref(), Some("开启"));
+        }
+    }
+
+    #[test]
+    fn write_then_read_round_trip() {
+        let catalog = catalog(vec![
+            Entry::new(None, "Tab\tand \"quote\"\n".to_string(), "制表\t和“引号”\n".to_string()),
+            Entry::new(Some("View".to_string()), "Zoom".to_string(), "缩放".to_string()),
+            plural("%d item", "%d items", &["%d 项"]),
+        ]);
+        let (bytes, _) = compile(&catalog, ByteOrder::Big);
+        let read = MoConverter::catalog_from_mo(&MoFile::from_bytes(bytes).unwrap(), "test.mo").unwrap();
+
+        assert_eq!(read.header.plural_forms(), Some("nplurals=2; plural=(n != 1);"));
+        let mut expected = catalog.entries.clone();
+        expected.sort_by_key(Entry::mo_msgid);
+        // 复数译文补齐到 nplurals
+        expected.iter_mut().filter(|e| e.msgid_plural.is_some()).for_each(|e| e.msgstr.resize(2, String::new()));
+        let key = |e: &Entry| (e.msgctxt.clone(), e.msgid.clone(), e.msgid_plural.clone(), e.msgstr.clone());
+        assert_eq!(read.entries.iter().map(key).collect::<Vec<_>>(), expected.iter().map(key).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn duplicate_keys_keep_last_entry() {
+        let catalog = catalog(vec![
+            Entry::new(None, "file".to_string(), "文件".to_string()),
+            plural("file", "files", &["个文件", "些文件"]),
+            Entry::new(None, "Save".to_string(), "保存".to_string()),
+            Entry::new(None, "Save".to_string(), "存储".to_string()),
+        ]);
+        let (bytes, report) = compile(&catalog, ByteOrder::Little);
+        assert_eq!(report.compiled, 2);
+        assert_eq!(gnu_lookup(&bytes, ByteOrder::Little, "file").as_deref(), Some("个文件\0些文件"));
+        assert_eq!(gnu_lookup(&bytes, ByteOrder::Little, "Save").as_deref(), Some("存储"));
+
+        let mo = MoFile::from_bytes(bytes).unwrap();
+        assert_eq!(mo.len(), 3);
+        let entry = mo.entry(mo.find(None, "file").unwrap()).unwrap();
+        assert_eq!(entry.msgid_plural.as_deref(), Some("files"));
+    }
+}
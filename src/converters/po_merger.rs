@@ -1,362 +1,173 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::sync::{Arc, Mutex};
-use std::error::Error;
-use rayon::prelude::*; // 添加 Rayon 支持
-
-// PO条目结构
-#[derive(Debug, Clone)]
-struct PoEntry {
-    msgctxt: Option<String>,  // 消息上下文
-    msgid: String,           // 原文
-    msgstr: String,         // 译文
-    comments: Vec<String>,  // 注释
-    is_fuzzy: bool,        // 是否为模糊翻译
-    line_number: usize,    // 在文件中的行号
-    source_file: String,   // 来源文件
+use serde::{Serialize, Deserialize};
+
+use super::catalog::{Catalog, Entry, MessageKey};
+use super::error::ConvertError;
+use super::po_parser::{self, Diagnostic, ParseMode, Severity};
+use super::po_writer::WriteOptions;
+
+/// 过时条目 (#~) 的处理方式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ObsoletePolicy {
+    #[default]
+    Drop,       // 丢弃过时条目
+    Keep,       // 保留为过时条目，写在输出文件末尾
+    Resurrect,  // 恢复为正常条目参与合并
 }
 
-impl Default for PoEntry {
-    fn default() -> Self {
-        Self {
-            msgctxt: None,
-            msgid: String::new(),
-            msgstr: String::new(),
-            comments: Vec::new(),
-            is_fuzzy: false,
-            line_number: 0,
-            source_file: String::new(),
-        }
-    }
+/// 同一文件中重复的 msgctxt+msgid 的处理方式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    FirstWins,  // 保留第一次出现的条目
+    #[default]
+    LastWins,   // 保留最后一次出现的条目
+    Error,      // 报告所有重复条目并停止处理
 }
 
-// 解析状态
-#[derive(PartialEq)]
-enum ParseState {
-    None,
-    Comment,
-    MsgCtxt,
-    MsgId,
-    MsgStr,
+/// 同一文件中 msgctxt+msgid 相同的一组条目，按在文件中出现的顺序排列
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    pub key: MessageKey,
+    pub entries: Vec<Entry>,
+    pub positions: Vec<usize>, // 各条目在所在目录中的位置
 }
 
-/// 合并多个PO文件
-/// 
-/// # 参数
-/// * `input_files` - 输入PO文件路径列表,按优先级排序(第一个最高)
-/// * `output_file` - 输出PO文件路径
-/// * `ignore_main_entries` - 是否保留第一个文件中已有的翻译
-/// 
-/// # 返回
-/// * `Result<(), String>` - 成功或错误信息
-pub fn merge_po_files(input_files: &[PathBuf], output_file: impl AsRef<Path>, ignore_main_entries: bool) -> Result<(), String> {
-    if input_files.is_empty() {
-        return Err("没有提供输入文件".to_string());
-    }
-
-    // 获取第一个文件的名称
-    let first_file_name = input_files[0].file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-
-    // 用于存储所有条目的HashMap
-    // key: (msgctxt, msgid), value: PoEntry
-    let entries: Arc<Mutex<HashMap<(Option<String>, String), PoEntry>>> = Arc::new(Mutex::new(HashMap::new()));
-    
-    // 记录第一个文件的头部信息
-    let header = Arc::new(Mutex::new(String::new()));
-    let has_header = Arc::new(Mutex::new(false));
-
-    // 并行处理所有输入文件
-    input_files.par_iter().enumerate().try_for_each(|(file_index, file_path)| {
-        let file = File::open(file_path).map_err(|e| format!("无法打开文件 {}: {}", file_path.display(), e))?;
-        let reader = BufReader::new(file);
-        let lines: Vec<String> = reader.lines()
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| format!("读取文件时出错: {}", e))?;
-        
-        let mut current_entries = Vec::new();
-        let mut current_entry = PoEntry::default();
-        let mut state = ParseState::None;
-        let mut line_number = 0;
-
-        current_entry.source_file = file_path.file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        for line in lines {
-            line_number += 1;
-            let trimmed = line.trim();
+/// 合并选项
+#[derive(Clone, Debug, Default)]
+pub struct MergeOptions {
+    pub ignore_main_entries: bool,      // 是否保留第一个文件中已有的翻译
+    pub obsolete_policy: ObsoletePolicy, // 过时条目的处理方式
+    pub parse_mode: ParseMode,          // 输入文件的解析模式
+    pub duplicate_policy: DuplicatePolicy, // 同一文件中重复条目的处理方式
+    pub write: WriteOptions,            // 输出PO文件的写出选项
+}
 
-            // 处理空行 - 表示一个条目的结束
-            if trimmed.is_empty() {
-                if !current_entry.msgid.is_empty() {
-                    // 处理头部信息
-                    if file_index == 0 && current_entry.msgid.is_empty() && !*has_header.lock().unwrap() {
-                        *header.lock().unwrap() = current_entry.msgstr.clone();
-                        *has_header.lock().unwrap() = true;
-                    } else {
-                        // 收集条目
-                        current_entries.push(current_entry.clone());
-                    }
+/// 按选项处理过时条目，恢复的过时条目不覆盖同一文件中的正常条目
+pub fn apply_obsolete_policy(entries: Vec<Entry>, policy: ObsoletePolicy) -> Vec<Entry> {
+    let live_keys: HashSet<MessageKey> = entries.iter()
+        .filter(|e| !e.obsolete)
+        .map(|e| e.key())
+        .collect();
+    entries.into_iter()
+        .filter_map(|mut entry| {
+            if entry.obsolete {
+                match policy {
+                    ObsoletePolicy::Drop => return None,
+                    ObsoletePolicy::Keep => {},
+                    ObsoletePolicy::Resurrect if live_keys.contains(&entry.key()) => return None,
+                    ObsoletePolicy::Resurrect => entry.obsolete = false,
                 }
-                current_entry = PoEntry {
-                    source_file: file_path.file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string(),
-                    line_number,
-                    ..Default::default()
-                };
-                state = ParseState::None;
-                continue;
             }
+            Some(entry)
+        })
+        .collect()
+}
 
-            // 处理各种类型的行
-            match trimmed {
-                s if s.starts_with('#') => {
-                    state = ParseState::Comment;
-                    current_entry.comments.push(s.to_string());
-                    if s.contains("fuzzy") {
-                        current_entry.is_fuzzy = true;
-                    }
-                },
-                s if s.starts_with("msgctxt ") => {
-                    state = ParseState::MsgCtxt;
-                    current_entry.msgctxt = Some(parse_po_string(&s["msgctxt ".len()..])?);
-                },
-                s if s.starts_with("msgid ") => {
-                    state = ParseState::MsgId;
-                    current_entry.msgid = parse_po_string(&s["msgid ".len()..])?;
-                },
-                s if s.starts_with("msgstr ") => {
-                    state = ParseState::MsgStr;
-                    current_entry.msgstr = parse_po_string(&s["msgstr ".len()..])?;
-                },
-                s if s.starts_with('"') => {
-                    // 继续前一个字符串
-                    let content = parse_po_string(s)?;
-                    match state {
-                        ParseState::MsgCtxt => {
-                            if let Some(ref mut ctx) = current_entry.msgctxt {
-                                ctx.push_str(&content);
-                            }
-                        },
-                        ParseState::MsgId => current_entry.msgid.push_str(&content),
-                        ParseState::MsgStr => current_entry.msgstr.push_str(&content),
-                        _ => return Err(format!("文件 {} 第 {} 行出现意外的字符串继续", file_path.display(), line_number)),
-                    }
-                },
-                _ => return Err(format!("文件 {} 第 {} 行格式错误: {}", file_path.display(), line_number, trimmed)),
-            }
-        }
-
-        if !current_entry.msgid.is_empty() {
-            current_entries.push(current_entry);
-        }
-
-        // 批量存储条目，减少锁竞争
-        store_entries(&entries, current_entries, file_index, ignore_main_entries, &first_file_name)?;
-        
-        Ok(())
-    })?;
-
-    // 写入合并后的文件
-    let mut output = File::create(&output_file)
-        .map_err(|e| format!("无法创建输出文件: {}", e))?;
-
-    // 写入头部信息
-    if *has_header.lock().unwrap() {
-        writeln!(output, "msgid \"\"").map_err(|e| format!("写入文件时出错: {}", e))?;
-        writeln!(output, "{}", *header.lock().unwrap()).map_err(|e| format!("写入文件时出错: {}", e))?;
-        writeln!(output).map_err(|e| format!("写入文件时出错: {}", e))?;
-    }
-
-    // 获取所有条目并排序
-    let entries_lock = entries.lock().unwrap();
-    let mut sorted_entries: Vec<_> = entries_lock.values().collect();
-    sorted_entries.sort_by(|a, b| {
-        if a.msgid.is_empty() { return std::cmp::Ordering::Less; }
-        if b.msgid.is_empty() { return std::cmp::Ordering::Greater; }
-        a.msgid.cmp(&b.msgid)
-    });
-
-    // 在sorted_entries排序之前添加调试信息
-    println!("Total entries before sorting: {}", sorted_entries.len());
-    println!("Entries with msgctxt:");
-    for entry in &sorted_entries {
-        if let Some(ref ctx) = entry.msgctxt {
-            println!("msgctxt: {}, msgid: {}", ctx, entry.msgid);
-        }
-    }
-
-    // 写入所有条目
-    for entry in sorted_entries {
-        // 写入注释
-        for comment in &entry.comments {
-            writeln!(output, "{}", comment).map_err(|e| format!("写入文件时出错: {}", e))?;
-        }
-
-        // 写入msgctxt(如果有)
-        if let Some(ref ctx) = entry.msgctxt {
-            write_po_string(&mut output, "msgctxt", ctx)?;
-        }
-
-        // 写入msgid
-        write_po_string(&mut output, "msgid", &entry.msgid)?;
-
-        // 写入msgstr
-        write_po_string(&mut output, "msgstr", &entry.msgstr)?;
-
-        // 条目之间的空行
-        writeln!(output).map_err(|e| format!("写入文件时出错: {}", e))?;
+/// 查找同一文件中重复的条目，过时条目不参与比较
+///
+/// 结果按每组第一个条目在文件中的位置排列
+pub fn find_duplicates(entries: &[Entry]) -> Vec<DuplicateGroup> {
+    let mut positions: HashMap<(Option<&str>, &str), Vec<usize>> = HashMap::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate().filter(|(_, e)| !e.obsolete) {
+        positions.entry(borrowed_key(entry)).or_default().push(i);
     }
 
-    // 验证输出文件
-    validate_po_file(&output_file)?;
-
-    Ok(())
+    let mut groups: Vec<Vec<usize>> = positions.into_values().filter(|p| p.len() > 1).collect();
+    groups.sort_unstable_by_key(|p| p[0]);
+    groups.into_iter()
+        .map(|positions| DuplicateGroup {
+            key: entries[positions[0]].key(),
+            entries: positions.iter().map(|&i| entries[i].clone()).collect(),
+            positions,
+        })
+        .collect()
 }
 
-// 批量存储PO条目
-fn store_entries(
-    entries: &Arc<Mutex<HashMap<(Option<String>, String), PoEntry>>>,
-    new_entries: Vec<PoEntry>,
-    file_index: usize,
-    ignore_main_entries: bool,
-    first_file_name: &str,
-) -> Result<(), String> {
-    let mut entries_lock = entries.lock().unwrap();
-    
-    for entry in new_entries {
-        let key = (entry.msgctxt.clone(), entry.msgid.clone());
-        
-        match entries_lock.get(&key) {
-            Some(existing) => {
-                // 如果设置了ignore_main_entries且现有条目来自第一个文件,则保留现有翻译
-                if ignore_main_entries && existing.source_file == first_file_name {
-                    continue;
-                }
-                
-                // 根据优先级决定是否覆盖
-                if file_index == 0 || !existing.is_fuzzy {
-                    entries_lock.insert(key, entry);
-                }
-            },
-            None => {
-                entries_lock.insert(key, entry);
-            }
-        }
-    }
-    
-    Ok(())
+/// 将重复条目报告为问题，每个重复出现的条目一条，同时给出第一次出现的行号
+pub fn duplicate_diagnostics(groups: &[DuplicateGroup]) -> Vec<Diagnostic> {
+    groups.iter()
+        .flat_map(|group| {
+            let first = &group.entries[0];
+            group.entries[1..].iter().map(move |entry| Diagnostic {
+                file: entry.source_file.clone(),
+                line: entry.line_number,
+                column: 1,
+                severity: Severity::Error,
+                message: format!("重复的条目 \"{}\"，第一次出现在第 {} 行", entry.msgid, first.line_number),
+            })
+        })
+        .collect()
 }
 
-// 解析PO字符串
-fn parse_po_string(s: &str) -> Result<String, String> {
-    if !s.starts_with('"') || !s.ends_with('"') {
-        return Err(format!("无效的PO字符串格式: {}", s));
+/// 按选项处理同一文件中的重复条目，保留的条目留在原来的位置
+///
+/// `DuplicatePolicy::Error` 时有重复条目则返回 [`ConvertError::Duplicates`]，带有所有重复条目的位置
+pub fn apply_duplicate_policy(entries: Vec<Entry>, policy: DuplicatePolicy) -> Result<Vec<Entry>, ConvertError> {
+    let groups = find_duplicates(&entries);
+    if groups.is_empty() {
+        return Ok(entries);
+    }
+    if policy == DuplicatePolicy::Error {
+        return Err(ConvertError::Duplicates(duplicate_diagnostics(&groups)));
     }
 
-    let content = &s[1..s.len()-1];
-    Ok(unescape_po_string(content))
-}
-
-// 转义PO字符串
-fn escape_po_string(s: &str) -> String {
-    s.replace('\\', "\\\\")
-     .replace('\"', "\\\"")
-     .replace('\n', "\\n")
-     .replace('\r', "\\r")
-     .replace('\t', "\\t")
+    // 每组中被丢弃的条目位置
+    let dropped: HashSet<usize> = groups.iter()
+        .flat_map(|group| {
+            let positions = &group.positions;
+            match policy {
+                DuplicatePolicy::FirstWins => &positions[1..],
+                _ => &positions[..positions.len() - 1],
+            }
+        })
+        .copied()
+        .collect();
+    Ok(entries.into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, entry)| entry)
+        .collect())
 }
 
-// 反转义PO字符串
-fn unescape_po_string(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    let mut chars = s.chars();
-    while let Some(c) = chars.next() {
-        if c == '\\' {
-            match chars.next() {
-                Some('\\') => result.push('\\'),
-                Some('\"') => result.push('\"'),
-                Some('n') => result.push('\n'),
-                Some('r') => result.push('\r'),
-                Some('t') => result.push('\t'),
-                Some(x) => result.push(x),
-                None => result.push('\\'),
-            }
-        } else {
-            result.push(c);
-        }
-    }
-    result
+// 不复制字符串的条目键
+fn borrowed_key(entry: &Entry) -> (Option<&str>, &str) {
+    (entry.msgctxt.as_deref(), entry.msgid.as_str())
 }
 
-// 写入PO字符串
-fn write_po_string(output: &mut File, key: &str, value: &str) -> Result<(), String> {
-    if value.contains('\n') {
-        // 多行字符串
-        writeln!(output, "{} \"\"", key)
-            .map_err(|e| format!("写入文件时出错: {}", e))?;
-        for line in value.split('\n') {
-            writeln!(output, "\"{}\\n\"", escape_po_string(line))
-                .map_err(|e| format!("写入文件时出错: {}", e))?;
-        }
+/// 冲突规则: `entry` 是否替换优先级更高的目录中已选中的同一条目
+///
+/// `existing` 为已选中的条目及其来源目录序号，目录必须按优先级从高到低依次处理，相同输入总是得到相同结果:
+/// * 优先级高(靠前)的目录中的条目优先
+/// * 例外: 已翻译且非模糊的条目会替换优先级更高目录中模糊或未翻译的同一条目，
+///   设置了 `ignore_main_entries` 时第一个目录中的条目不会被替换
+/// * 正常条目总是替换过时条目，过时条目不会替换正常条目
+pub fn replaces(existing: Option<(usize, &Entry)>, entry: &Entry, ignore_main_entries: bool) -> bool {
+    let Some((existing_index, existing)) = existing else {
+        return true;
+    };
+
+    if entry.obsolete != existing.obsolete {
+        // 正常条目总是覆盖过时条目，过时条目不能覆盖正常条目
+        existing.obsolete
+    } else if ignore_main_entries && existing_index == 0 {
+        // 保留第一个文件中已有的翻译
+        false
     } else {
-        // 单行字符串
-        writeln!(output, "{} \"{}\"", key, escape_po_string(value))
-            .map_err(|e| format!("写入文件时出错: {}", e))?;
+        // 优先级高的条目只在模糊或未翻译时被已完成的翻译替换
+        let complete = |e: &Entry| e.is_translated() && !e.is_fuzzy();
+        !complete(existing) && complete(entry)
     }
-    Ok(())
 }
 
-// 验证PO文件
-fn validate_po_file(file_path: impl AsRef<Path>) -> Result<(), String> {
-    let file = File::open(file_path.as_ref())
-        .map_err(|e| format!("无法打开文件进行验证: {}", e))?;
-    let reader = BufReader::new(file);
-    let mut line_number = 0;
-    let mut in_entry = false;
-    let mut has_msgid = false;
-    let mut has_msgstr = false;
-
-    for line in reader.lines() {
-        line_number += 1;
-        let line = line.map_err(|e| format!("验证时读取文件出错: {}", e))?;
-        let trimmed = line.trim();
-
-        if trimmed.is_empty() {
-            if in_entry {
-                if !has_msgid || !has_msgstr {
-                    return Err(format!("第 {} 行: 不完整的PO条目", line_number));
-                }
-                in_entry = false;
-                has_msgid = false;
-                has_msgstr = false;
-            }
-            continue;
-        }
-
-        if trimmed.starts_with("msgid ") {
-            in_entry = true;
-            has_msgid = true;
-        } else if trimmed.starts_with("msgstr ") {
-            if !has_msgid {
-                return Err(format!("第 {} 行: msgstr前缺少msgid", line_number));
-            }
-            has_msgstr = true;
-        }
-    }
-
-    // 检查最后一个条目
-    if in_entry && (!has_msgid || !has_msgstr) {
-        return Err("文件末尾存在不完整的PO条目".to_string());
-    }
+/// 各文件的文件名，用于在头部中记录合并进来的文件
+pub fn file_names(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter()
+        .map(|path| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+        .collect()
+}
 
-    Ok(())
-} 
\ No newline at end of file
+/// 以严格模式解析PO文件为翻译目录，遇到第一个格式问题即返回错误
+pub fn parse_po_file(file_path: &Path) -> Result<Catalog, ConvertError> {
+    po_parser::parse_file(file_path, ParseMode::Strict).map(|result| result.catalog)
+}
@@ -0,0 +1,821 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::Path;
+
+use encoding_rs::{Encoding, UTF_8};
+use serde::{Serialize, Deserialize};
+
+use super::catalog::{Catalog, Entry};
+use super::charset;
+use super::error::{ConvertError, IoOp};
+use super::header::Header;
+
+/// 解析模式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    Strict,     // 遇到第一个问题即返回错误
+    #[default]
+    Lenient,    // 记录所有问题，跳过出错的条目继续解析
+}
+
+/// 问题的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Severity {
+    Warning, // 不影响使用，但可能不符合预期
+    #[default]
+    Error,   // 条目被跳过或会导致运行时出错
+}
+
+/// 解析或校验过程中发现的问题，行号和列号从1开始
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message),
+            Severity::Warning => write!(f, "{}:{}:{}: 警告: {}", self.file, self.line, self.column, self.message),
+        }
+    }
+}
+
+/// 解析结果: 翻译目录和宽松模式下收集到的问题
+pub struct ParseResult {
+    pub catalog: Catalog,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// 解析PO文件为翻译目录
+///
+/// 第一个 msgid 为空且没有 msgctxt 的条目作为头部，其余条目按文件中的顺序保存。
+/// 文件按头部声明的 charset 解码，头部的 charset 会改写为UTF-8
+pub fn parse_file(path: &Path, mode: ParseMode) -> Result<ParseResult, ConvertError> {
+    let file = File::open(path).map_err(|e| ConvertError::io(IoOp::Open, path, e))?;
+    let file_name = path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    parse_reader(BufReader::with_capacity(charset::HEADER_PROBE_LEN, file), &file_name, mode)
+}
+
+/// 从任意输入流解析PO内容，`file` 用于条目来源和诊断信息
+///
+/// 严格模式下返回 [`ConvertError::Parse`]，读取失败也作为该位置的问题报告
+pub fn parse_reader<R: BufRead>(reader: R, file: &str, mode: ParseMode) -> Result<ParseResult, ConvertError> {
+    let mut catalog = Catalog::new();
+    let mut has_header = false;
+    let mut diagnostics = Vec::new();
+
+    for item in PoParser::new(reader, file) {
+        match item {
+            Ok(entry) => add_entry(&mut catalog, &mut has_header, entry),
+            Err(diagnostic) if mode == ParseMode::Strict => return Err(ConvertError::Parse(diagnostic)),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+
+    // 内容已转为UTF-8，头部同步改写
+    if catalog.header.charset().is_some() {
+        catalog.header.set_charset("UTF-8");
+    }
+
+    Ok(ParseResult { catalog, diagnostics })
+}
+
+// 头部条目存入catalog.header，其余空msgid条目忽略
+fn add_entry(catalog: &mut Catalog, has_header: &mut bool, entry: Entry) {
+    if entry.msgid.is_empty() {
+        // 头部条目只取第一个
+        if entry.msgctxt.is_none() && !entry.obsolete && !*has_header {
+            catalog.header = Header::parse(entry.msgstr());
+            catalog.header_comments = entry.comments;
+            *has_header = true;
+        }
+        return;
+    }
+
+    catalog.entries.push(entry);
+}
+
+// 解析状态
+#[derive(Clone, Copy, PartialEq)]
+enum ParseState {
+    None,
+    Comment,
+    MsgCtxt,
+    MsgId,
+    MsgIdPlural,
+    MsgStr,
+}
+
+// msgstr[n] 允许的复数形式数量上限，避免损坏的文件按巨大的索引分配译文
+const MAX_PLURAL_FORMS: usize = 32;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Keyword {
+    Ctxt,
+    Id,
+    IdPlural,
+    Str,
+    StrIndex(usize),
+}
+
+// 一行PO文本的记号
+enum Token {
+    Blank,                  // 空行，表示条目结束
+    Empty,                  // 只有 "#~" 标记的行
+    Comment(String),        // 译者、提取、引用和标记注释
+    Keyword { previous: bool, keyword: Keyword, value: String }, // 关键字行，previous 表示 "#|"
+    Continuation { previous: bool, value: String },             // 字符串续行
+}
+
+// 按字节偏移计算列号
+fn column_at(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+// 将一行拆分为记号，返回 (是否过时, 记号)；出错时返回 (字节偏移, 错误信息)
+fn tokenize(line: &str) -> Result<(bool, Token), (usize, String)> {
+    let mut rest = line.trim_start();
+    if rest.trim_end().is_empty() {
+        return Ok((false, Token::Blank));
+    }
+
+    let mut obsolete = false;
+    let mut previous = false;
+    if let Some(after) = rest.strip_prefix("#~") {
+        obsolete = true;
+        rest = after;
+        if let Some(after) = rest.strip_prefix('|') {
+            previous = true;
+            rest = after;
+        }
+        rest = rest.trim_start();
+        if rest.trim_end().is_empty() {
+            return Ok((true, Token::Empty));
+        }
+    } else if let Some(after) = rest.strip_prefix("#|") {
+        previous = true;
+        rest = after.trim_start();
+    }
+
+    if !previous && rest.starts_with('#') {
+        return Ok((obsolete, Token::Comment(rest.trim_end().to_string())));
+    }
+
+    let offset = line.len() - rest.len();
+    if rest.starts_with('"') {
+        let value = parse_quoted(line, offset)?;
+        return Ok((obsolete, Token::Continuation { previous, value }));
+    }
+
+    // 关键字到第一个空白或引号为止
+    let word_len = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+    let word = &rest[..word_len];
+    let keyword = match word {
+        "msgctxt" => Keyword::Ctxt,
+        "msgid" => Keyword::Id,
+        "msgid_plural" => Keyword::IdPlural,
+        "msgstr" => Keyword::Str,
+        _ => match word.strip_prefix("msgstr[").and_then(|s| s.strip_suffix(']')) {
+            Some(index) => match index.parse() {
+                Ok(index) if index < MAX_PLURAL_FORMS => Keyword::StrIndex(index),
+                Ok(_) => return Err((offset + "msgstr[".len(), format!("复数索引过大: {} (最多 {} 个复数形式)", word, MAX_PLURAL_FORMS))),
+                Err(_) => return Err((offset, format!("复数索引无效: {}", word))),
+            },
+            None if word.is_empty() => return Err((offset, format!("无法识别的内容: {}", rest.trim_end()))),
+            None => return Err((offset, format!("无法识别的关键字: {}", word))),
+        },
+    };
+    if previous && matches!(keyword, Keyword::Str | Keyword::StrIndex(_)) {
+        return Err((offset, "#| 注释中不能出现 msgstr".to_string()));
+    }
+
+    let value_start = offset + word_len + (rest[word_len..].len() - rest[word_len..].trim_start().len());
+    if !line[value_start..].starts_with('"') {
+        return Err((value_start, format!("{} 后缺少字符串", word)));
+    }
+    let value = parse_quoted(line, value_start)?;
+
+    Ok((obsolete, Token::Keyword { previous, keyword, value }))
+}
+
+// 解析从 `start` 开始的带引号字符串，结束引号后只允许空白
+fn parse_quoted(line: &str, start: usize) -> Result<String, (usize, String)> {
+    let mut result = String::new();
+    let mut chars = line[start + 1..].char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, '\\')) => result.push('\\'),
+                Some((_, '"')) => result.push('"'),
+                Some((_, 'n')) => result.push('\n'),
+                Some((_, 'r')) => result.push('\r'),
+                Some((_, 't')) => result.push('\t'),
+                Some((_, 'a')) => result.push('\x07'),
+                Some((_, 'b')) => result.push('\x08'),
+                Some((_, 'f')) => result.push('\x0C'),
+                Some((_, 'v')) => result.push('\x0B'),
+                // 八进制 \NNN 和十六进制 \xHH，只支持ASCII范围内的值
+                Some((_, first @ ('0'..='7' | 'x'))) => {
+                    let (radix, max_digits, mut value) = match first {
+                        'x' => (16, 2, 0),
+                        digit => (8, 2, digit.to_digit(8).unwrap_or(0)),
+                    };
+                    let mut digits = 0;
+                    while let Some(digit) = chars.peek().and_then(|(_, c)| c.to_digit(radix)) {
+                        if digits == max_digits {
+                            break;
+                        }
+                        value = value * radix + digit;
+                        digits += 1;
+                        chars.next();
+                    }
+                    match char::from_u32(value).filter(char::is_ascii) {
+                        Some(c) if first != 'x' || digits > 0 => result.push(c),
+                        _ => return Err((start + 1 + index, "无效的转义序列".to_string())),
+                    }
+                }
+                Some((_, x)) => result.push(x),
+                None => result.push('\\'),
+            },
+            '"' => {
+                let end = start + 1 + index + 1;
+                let trailing = &line[end..];
+                if !trailing.trim().is_empty() {
+                    let extra = end + (trailing.len() - trailing.trim_start().len());
+                    return Err((extra, "字符串结束引号后有多余内容".to_string()));
+                }
+                return Ok(result);
+            }
+            _ => result.push(c),
+        }
+    }
+
+    Err((start, "字符串缺少结束引号".to_string()))
+}
+
+// 没有空行分隔时，这些记号表示一个新条目的开始
+fn starts_entry(token: &Token) -> bool {
+    match token {
+        Token::Comment(_) => true,
+        Token::Keyword { previous, keyword, .. } => {
+            *previous || matches!(keyword, Keyword::Ctxt | Keyword::Id)
+        }
+        Token::Continuation { previous, .. } => *previous,
+        Token::Blank | Token::Empty => false,
+    }
+}
+
+// 解析注释行，按注释类型分别保存
+fn parse_comment(entry: &mut Entry, line: &str) {
+    if let Some(flags) = line.strip_prefix("#,") {
+        for flag in flags.split(',').map(|f| f.trim()).filter(|f| !f.is_empty()) {
+            if !entry.has_flag(flag) {
+                entry.flags.push(flag.to_string());
+            }
+        }
+    } else if let Some(references) = line.strip_prefix("#:") {
+        entry.references.extend(references.split_whitespace().map(|r| r.to_string()));
+    } else if let Some(extracted) = line.strip_prefix("#.") {
+        entry.extracted_comments.push(strip_comment_space(extracted).to_string());
+    } else {
+        entry.comments.push(strip_comment_space(&line[1..]).to_string());
+    }
+}
+
+// 去掉注释标记后的一个空格
+fn strip_comment_space(s: &str) -> &str {
+    s.strip_prefix(' ').unwrap_or(s)
+}
+
+// 一行解码后的文本
+struct RawLine {
+    number: usize,
+    text: String,
+    decode_error: Option<(usize, String)>, // (列号, 错误信息)
+}
+
+// 按行读取并解码输入，编码由BOM或头部 charset 决定
+struct LineReader<R> {
+    reader: R,
+    encoding: &'static Encoding,
+    decoded: Option<Cursor<Vec<u8>>>, // 非ASCII兼容编码(如UTF-16)的内容整体解码后按UTF-8读取
+    initialized: bool,
+    buffer: Vec<u8>,
+    line_number: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            encoding: UTF_8,
+            decoded: None,
+            initialized: false,
+            buffer: Vec::new(),
+            line_number: 0,
+        }
+    }
+
+    // 根据缓冲区中的文件开头确定编码
+    fn initialize(&mut self) -> Result<(), ConvertError> {
+        self.initialized = true;
+
+        let probe = self.reader.fill_buf().map_err(ConvertError::read)?;
+        let (encoding, bom_len) = charset::probe_po_encoding(probe)?;
+        self.reader.consume(bom_len);
+        self.encoding = encoding;
+
+        if !encoding.is_ascii_compatible() {
+            let mut bytes = Vec::new();
+            self.reader.read_to_end(&mut bytes).map_err(ConvertError::read)?;
+            let text = charset::decode(&bytes, encoding)?.into_owned();
+            self.decoded = Some(Cursor::new(text.into_bytes()));
+            self.encoding = UTF_8;
+        }
+        Ok(())
+    }
+
+    fn next_line(&mut self) -> Option<Result<RawLine, ConvertError>> {
+        if !self.initialized {
+            if let Err(e) = self.initialize() {
+                return Some(Err(e));
+            }
+        }
+
+        self.buffer.clear();
+        let read = match &mut self.decoded {
+            Some(cursor) => cursor.read_until(b'\n', &mut self.buffer),
+            None => self.reader.read_until(b'\n', &mut self.buffer),
+        };
+        match read {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(ConvertError::read(e))),
+        }
+
+        while matches!(self.buffer.last(), Some(b'\n' | b'\r')) {
+            self.buffer.pop();
+        }
+        self.line_number += 1;
+
+        let (text, decode_error) = Self::decode_line(&self.buffer, self.encoding);
+        Some(Ok(RawLine { number: self.line_number, text, decode_error }))
+    }
+
+    // 解码一行，遇到无效字符时用替换字符继续并报告位置
+    fn decode_line(bytes: &[u8], encoding: &'static Encoding) -> (String, Option<(usize, String)>) {
+        if encoding == UTF_8 {
+            return match std::str::from_utf8(bytes) {
+                Ok(text) => (text.to_string(), None),
+                Err(e) => {
+                    let valid = std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default();
+                    let column = valid.chars().count() + 1;
+                    (String::from_utf8_lossy(bytes).into_owned(), Some((column, "无效的UTF-8字符".to_string())))
+                }
+            };
+        }
+
+        match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+            Some(text) => (text.into_owned(), None),
+            None => {
+                let (text, _) = encoding.decode_without_bom_handling(bytes);
+                (text.into_owned(), Some((1, format!("无效的{}字符", encoding.name()))))
+            }
+        }
+    }
+}
+
+/// 流式PO解析器
+///
+/// 逐行读取输入，每解析完一个条目就产出一个 `Ok(Entry)`(包括msgid为空的头部条目)，
+/// 每发现一个问题就产出一个 `Err(Diagnostic)`。出错的条目会被整体跳过，
+/// 解析从下一个条目继续
+pub struct PoParser<R> {
+    lines: LineReader<R>,
+    file: String,
+    entry: Entry,
+    has_msgid: bool,
+    broken: bool,                    // 当前条目出错，结束时丢弃
+    broken_after_msgstr: bool,       // 出错的条目已出现过 msgstr，下一个条目开头可以结束它
+    state: ParseState,
+    previous_field: Option<Keyword>, // 最近一个 "#|" 关键字，用于续行
+    msgstr_index: usize,
+    pending: VecDeque<Result<Entry, Diagnostic>>,
+    finished: bool,
+}
+
+impl<R: BufRead> PoParser<R> {
+    pub fn new(reader: R, file: &str) -> Self {
+        Self {
+            lines: LineReader::new(reader),
+            file: file.to_string(),
+            entry: Self::empty_entry(file),
+            has_msgid: false,
+            broken: false,
+            broken_after_msgstr: false,
+            state: ParseState::None,
+            previous_field: None,
+            msgstr_index: 0,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn empty_entry(file: &str) -> Entry {
+        Entry { source_file: file.to_string(), ..Default::default() }
+    }
+
+    fn report(&mut self, line: usize, column: usize, message: String) {
+        self.pending.push_back(Err(Diagnostic { file: self.file.clone(), line, column, severity: Severity::Error, message }));
+    }
+
+    // 报告错误并丢弃所在条目，同一条目中后续的问题不再报告
+    fn error(&mut self, line: usize, column: usize, message: String) {
+        if !self.broken {
+            self.report(line, column, message);
+        }
+        self.broken = true;
+        self.broken_after_msgstr = self.state == ParseState::MsgStr;
+    }
+
+    // 报告无法解析的行；除字符串续行外，出现在完整条目之后的错误行归属下一个条目
+    fn line_error(&mut self, line: &RawLine, column: usize, message: String) {
+        let content = line.text.trim_start();
+        let content = content.strip_prefix("#~").map(|s| s.trim_start()).unwrap_or(content);
+        if self.state == ParseState::MsgStr && !content.starts_with('"') {
+            self.finish_entry();
+        }
+        self.error(line.number, column, message);
+        if content.starts_with("msgstr") {
+            self.broken_after_msgstr = true;
+        }
+    }
+
+    // 结束当前条目
+    fn finish_entry(&mut self) {
+        let entry = std::mem::replace(&mut self.entry, Self::empty_entry(&self.file));
+        let has_msgid = std::mem::take(&mut self.has_msgid);
+        let broken = std::mem::take(&mut self.broken);
+        self.broken_after_msgstr = false;
+        self.state = ParseState::None;
+        self.previous_field = None;
+
+        if broken || !has_msgid {
+            return;
+        }
+        if entry.msgstr.is_empty() {
+            let line = entry.line_number;
+            self.report(line, 1, format!("条目 \"{}\" 缺少 msgstr", entry.msgid));
+            return;
+        }
+        self.pending.push_back(Ok(entry));
+    }
+
+    fn process_line(&mut self, line: RawLine) {
+        let tokenized = tokenize(&line.text);
+
+        // 出错的条目跳过到空行，或跳过到 msgstr 之后出现的下一个条目开头
+        if self.broken {
+            match &tokenized {
+                Ok((_, Token::Blank)) => {
+                    self.finish_entry();
+                    return;
+                }
+                Ok((_, token)) if starts_entry(token) && self.broken_after_msgstr => self.finish_entry(),
+                Ok((_, Token::Keyword { keyword: Keyword::Str | Keyword::StrIndex(_), .. })) => {
+                    self.broken_after_msgstr = true;
+                    return;
+                }
+                _ => return,
+            }
+        }
+
+        if let Some((column, message)) = line.decode_error.clone() {
+            self.line_error(&line, column, message);
+        }
+
+        let (obsolete, token) = match tokenized {
+            Ok(token) => token,
+            Err((offset, message)) => {
+                let column = column_at(&line.text, offset);
+                self.line_error(&line, column, message);
+                return;
+            }
+        };
+
+        // 没有空行分隔时，新的注释或关键字同样表示上一个条目结束
+        if starts_entry(&token) && self.state == ParseState::MsgStr {
+            self.finish_entry();
+        }
+
+        if !matches!(token, Token::Blank | Token::Empty) {
+            if self.entry.line_number == 0 {
+                self.entry.line_number = line.number;
+            }
+            if obsolete {
+                self.entry.obsolete = true;
+            }
+        }
+
+        let column = column_at(&line.text, line.text.len() - line.text.trim_start().len());
+        match token {
+            Token::Blank => self.finish_entry(),
+            Token::Empty => {}
+            Token::Comment(text) => {
+                parse_comment(&mut self.entry, &text);
+                if self.state == ParseState::None {
+                    self.state = ParseState::Comment;
+                }
+            }
+            Token::Keyword { previous: true, keyword, value } => {
+                match keyword {
+                    Keyword::Ctxt => self.entry.previous_msgctxt = Some(value),
+                    Keyword::Id => self.entry.previous_msgid = Some(value),
+                    _ => self.entry.previous_msgid_plural = Some(value),
+                }
+                self.previous_field = Some(keyword);
+                self.state = ParseState::Comment;
+            }
+            Token::Continuation { previous: true, value } => {
+                let target = match self.previous_field {
+                    Some(Keyword::Ctxt) => &mut self.entry.previous_msgctxt,
+                    Some(Keyword::Id) => &mut self.entry.previous_msgid,
+                    Some(_) => &mut self.entry.previous_msgid_plural,
+                    None => {
+                        self.error(line.number, column, "意外的 #| 字符串续行".to_string());
+                        return;
+                    }
+                };
+                if let Some(text) = target {
+                    text.push_str(&value);
+                }
+            }
+            Token::Keyword { previous: false, keyword, value } => {
+                self.process_keyword(line.number, column, keyword, value);
+            }
+            Token::Continuation { previous: false, value } => {
+                let target = match self.state {
+                    ParseState::MsgCtxt => self.entry.msgctxt.as_mut(),
+                    ParseState::MsgId => Some(&mut self.entry.msgid),
+                    ParseState::MsgIdPlural => self.entry.msgid_plural.as_mut(),
+                    ParseState::MsgStr => self.entry.msgstr.get_mut(self.msgstr_index),
+                    ParseState::None | ParseState::Comment => None,
+                };
+                match target {
+                    Some(text) => text.push_str(&value),
+                    None => self.error(line.number, column, "意外的字符串续行".to_string()),
+                }
+            }
+        }
+    }
+
+    fn process_keyword(&mut self, line: usize, column: usize, keyword: Keyword, value: String) {
+        let state = self.state;
+        let expected = match keyword {
+            Keyword::Ctxt => matches!(state, ParseState::None | ParseState::Comment),
+            Keyword::Id => matches!(state, ParseState::None | ParseState::Comment | ParseState::MsgCtxt),
+            Keyword::IdPlural => state == ParseState::MsgId,
+            Keyword::Str => state == ParseState::MsgId,
+            Keyword::StrIndex(_) => matches!(state, ParseState::MsgIdPlural | ParseState::MsgStr)
+                && self.entry.msgid_plural.is_some(),
+        };
+        if !expected {
+            let message = match keyword {
+                Keyword::Ctxt => "意外的 msgctxt".to_string(),
+                Keyword::Id => "意外的 msgid (上一个条目缺少 msgstr?)".to_string(),
+                Keyword::IdPlural => "msgid_plural 必须紧跟在 msgid 之后".to_string(),
+                Keyword::Str if state == ParseState::MsgIdPlural => "复数条目应使用 msgstr[n]".to_string(),
+                Keyword::Str => "意外的 msgstr".to_string(),
+                Keyword::StrIndex(_) => "只有复数条目才能使用 msgstr[n]".to_string(),
+            };
+            self.error(line, column, message);
+            return;
+        }
+
+        match keyword {
+            Keyword::Ctxt => {
+                self.entry.msgctxt = Some(value);
+                self.state = ParseState::MsgCtxt;
+            }
+            Keyword::Id => {
+                self.entry.msgid = value;
+                self.has_msgid = true;
+                self.state = ParseState::MsgId;
+            }
+            Keyword::IdPlural => {
+                self.entry.msgid_plural = Some(value);
+                self.state = ParseState::MsgIdPlural;
+            }
+            Keyword::Str => {
+                self.entry.msgstr = vec![value];
+                self.msgstr_index = 0;
+                self.state = ParseState::MsgStr;
+            }
+            Keyword::StrIndex(index) => {
+                if self.entry.msgstr.len() <= index {
+                    self.entry.msgstr.resize(index + 1, String::new());
+                }
+                self.entry.msgstr[index] = value;
+                self.msgstr_index = index;
+                self.state = ParseState::MsgStr;
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PoParser<R> {
+    type Item = Result<Entry, Diagnostic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.lines.next_line() {
+                Some(Ok(line)) => self.process_line(line),
+                Some(Err(error)) => {
+                    // 读取失败时无法继续，丢弃未完成的条目
+                    let line = self.lines.line_number + 1;
+                    self.report(line, 1, error.to_string());
+                    self.finished = true;
+                }
+                None => {
+                    self.finish_entry();
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::converters::po_writer::{self, WriteOptions};
+
+    const SAMPLE: &str = r#"# Translator comment
+msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. extracted
+#: src/main.c:10 src/main.c:20
+#, fuzzy, c-format
+#| msgctxt "Menu"
+#| msgid "Old %s"
+msgctxt "Menu"
+msgid "Open %s"
+msgstr "打开 %s"
+
+msgid "Tab\there \"quoted\" back\\slash\a\001"
+msgstr ""
+"第一行\n"
+"第二行"
+
+msgid "%d file"
+msgid_plural "%d files"
+msgstr[0] "%d 个文件"
+msgstr[1] "%d 个文件们"
+
+#~ msgid "Removed"
+#~ msgstr "已删除"
+
+#~| msgid "Older"
+#~ msgid "Old plural"
+#~ msgid_plural "Old plurals"
+#~ msgstr[0] "旧"
+#~ msgstr[1] "旧的"
+"#;
+
+    fn parse(text: &str, mode: ParseMode) -> Result<ParseResult, ConvertError> {
+        parse_reader(Cursor::new(text.as_bytes()), "test.po", mode)
+    }
+
+    fn write(catalog: &Catalog) -> String {
+        let mut output = Vec::new();
+        po_writer::write_catalog(&mut output, catalog, &WriteOptions::input_order()).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn parse_error(text: &str) -> Diagnostic {
+        match parse(text, ParseMode::Strict) {
+            Err(ConvertError::Parse(diagnostic)) => diagnostic,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn parses_all_entry_parts() {
+        let catalog = parse(SAMPLE, ParseMode::Strict).unwrap().catalog;
+        assert_eq!(catalog.header_comments, ["Translator comment"]);
+        assert_eq!(catalog.nplurals(), 2);
+        assert_eq!(catalog.len(), 5);
+
+        let open = &catalog.entries[0];
+        assert_eq!(open.key(), (Some("Menu".to_string()), "Open %s".to_string()));
+        assert_eq!(open.extracted_comments, ["extracted"]);
+        assert_eq!(open.references, ["src/main.c:10", "src/main.c:20"]);
+        assert_eq!(open.flags, ["fuzzy", "c-format"]);
+        assert_eq!(open.previous_msgctxt.as_deref(), Some("Menu"));
+        assert_eq!(open.previous_msgid.as_deref(), Some("Old %s"));
+        assert_eq!(open.line_number, 7);
+
+        let escaped = &catalog.entries[1];
+        assert_eq!(escaped.msgid, "Tab\there \"quoted\" back\\slash\x07\x01");
+        assert_eq!(escaped.msgstr, ["第一行\n第二行"]);
+
+        assert_eq!(catalog.entries[2].msgstr, ["%d 个文件", "%d 个文件们"]);
+
+        let removed = &catalog.entries[3];
+        assert!(removed.obsolete);
+        assert_eq!(removed.msgstr, ["已删除"]);
+        let old_plural = &catalog.entries[4];
+        assert!(old_plural.obsolete);
+        assert_eq!(old_plural.previous_msgid.as_deref(), Some("Older"));
+        assert_eq!(old_plural.msgid_plural.as_deref(), Some("Old plurals"));
+        assert_eq!(old_plural.msgstr, ["旧", "旧的"]);
+    }
+
+    #[test]
+    fn write_round_trip_is_lossless() {
+        let catalog = parse(SAMPLE, ParseMode::Strict).unwrap().catalog;
+        let written = write(&catalog);
+        assert_eq!(written, SAMPLE);
+
+        let reparsed = parse(&written, ParseMode::Strict).unwrap().catalog;
+        assert_eq!(reparsed.header, catalog.header);
+        assert_eq!(reparsed.entries, catalog.entries);
+    }
+
+    #[test]
+    fn round_trip_preserves_every_control_character() {
+        let mut catalog = Catalog::new();
+        catalog.header = Header::minimal();
+        let msgid: String = (1u8..0x80).map(char::from).collect();
+        catalog.entries.push(Entry::new(None, msgid.clone(), format!("{} 中文 \\n", msgid)));
+        let reparsed = parse(&write(&catalog), ParseMode::Strict).unwrap().catalog;
+        assert_eq!(reparsed.entries[0].msgid, msgid);
+        assert_eq!(reparsed.entries[0].msgstr, catalog.entries[0].msgstr);
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let diagnostic = parse_error("msgid \"a\"\nmsgstr \"b\n");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 8));
+        assert_eq!(diagnostic.file, "test.po");
+
+        let diagnostic = parse_error("msgid \"a\"\nmsgstr \"b\" x\n");
+        assert_eq!((diagnostic.line, diagnostic.column), (2, 12));
+
+        let diagnostic = parse_error("msgid \"a\"\nmsgstr[0] \"b\"\n");
+        assert_eq!(diagnostic.line, 2);
+        assert!(diagnostic.message.contains("msgstr[n]"), "{}", diagnostic.message);
+    }
+
+    #[test]
+    fn rejects_huge_plural_index() {
+        let text = "msgid \"a\"\nmsgid_plural \"b\"\nmsgstr[0] \"x\"\n  msgstr[999999999] \"y\"\n";
+        let diagnostic = parse_error(text);
+        assert_eq!((diagnostic.line, diagnostic.column), (4, 10));
+        assert!(diagnostic.message.contains("msgstr[999999999]"), "{}", diagnostic.message);
+
+        let text = format!("msgid \"a\"\nmsgid_plural \"b\"\nmsgstr[{}] \"x\"\n", MAX_PLURAL_FORMS - 1);
+        let catalog = parse(&text, ParseMode::Strict).unwrap().catalog;
+        assert_eq!(catalog.entries[0].msgstr.len(), MAX_PLURAL_FORMS);
+    }
+
+    #[test]
+    fn lenient_mode_skips_broken_entries() {
+        let text = "msgid \"a\"\nmsgstr \"1\"\n\nmsgid \"b\"\nmsgid_plural \"bs\"\nmsgstr[99999] \"2\"\n\nmsgid \"c\"\nmsgstr \"3\n\nmsgid \"d\"\nmsgstr \"4\"\n";
+        let result = parse(text, ParseMode::Lenient).unwrap();
+        let msgids: Vec<_> = result.catalog.entries.iter().map(|e| e.msgid.as_str()).collect();
+        assert_eq!(msgids, ["a", "d"]);
+        let lines: Vec<_> = result.diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [6, 9]);
+        assert!(result.diagnostics.iter().all(Diagnostic::is_error));
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use super::catalog::{Catalog, Entry, MessageKey};
+use super::error::ConvertError;
+use super::header::POT_CREATION_DATE;
+use super::mo_converter::MoConverter;
+use super::po_merger::{self, DuplicatePolicy};
+use super::po_writer::{self, WriteOptions};
+
+/// 与 msgmerge 相同，相似度不低于此值的原文才视为改写过的同一条消息
+pub const DEFAULT_MIN_SIMILARITY: f64 = 0.6;
+
+/// 语言包更新选项
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UpdateOptions {
+    pub fuzzy_matching: bool, // 是否为原文已不存在的条目查找相似的新原文
+    pub min_similarity: f64,  // 模糊匹配的最低相似度，0到1
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        Self {
+            fuzzy_matching: true,
+            min_similarity: DEFAULT_MIN_SIMILARITY,
+        }
+    }
+}
+
+/// 语言包更新的结果
+#[derive(Clone, Debug, Default)]
+pub struct UpdateReport {
+    pub kept: usize,                          // 原文仍在基础目录中的条目数，含恢复的过时条目
+    pub fuzzy: Vec<(MessageKey, MessageKey)>, // 译文转到相似新原文的条目 (旧键, 新键)，已标记为fuzzy
+    pub obsolete: Vec<MessageKey>,            // 原文已不在基础目录中、标记为过时的条目
+}
+
+/// 按新的基础目录更新语言包PO文件，类似 `msgmerge --update`，结果写入 `output`
+///
+/// `base` 为新版本的 `.mo` 或 `.pot` 文件，按扩展名区分，其余扩展名按PO格式解析。
+/// 语言包中重复的条目以最后一个为准
+pub fn update_po_file(mod_file: &Path, base: &Path, output: &Path, options: &UpdateOptions, write: &WriteOptions) -> Result<UpdateReport, ConvertError> {
+    let mut mod_catalog = po_merger::parse_po_file(mod_file)?;
+    mod_catalog.entries = po_merger::apply_duplicate_policy(mod_catalog.entries, DuplicatePolicy::LastWins)?;
+
+    let (updated, report) = update_catalog(&mod_catalog, &read_base(base)?, options);
+    po_writer::write_po_file(&updated, output, write)?;
+    Ok(report)
+}
+
+/// 读取基础目录: `.mo` 文件按MO格式读取，其余按PO格式解析
+pub fn read_base(path: &Path) -> Result<Catalog, ConvertError> {
+    let is_mo = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mo"));
+    if is_mo {
+        MoConverter::read_mo_file(path)
+    } else {
+        po_merger::parse_po_file(path)
+    }
+}
+
+/// 按新的基础目录更新语言包，条目按 msgctxt+msgid 对应
+///
+/// * 原文仍在基础目录中的条目保留译文；复数原文采用基础目录中的值，有变化时标记为fuzzy；
+///   源码位置引用和提取的注释在基础目录中非空时采用新值
+/// * 原文已不存在的已翻译条目，在上下文相同、尚未被语言包使用的新原文中查找最相似的一个，
+///   相似度达到 `min_similarity` 时改用新原文，标记为fuzzy，旧原文记入 `#|`；
+///   多个条目找到同一个新原文时相似度最高的优先
+/// * 其余原文已不存在的条目标记为过时 (#~)
+/// * 过时条目的原文重新出现在基础目录中、且没有正常条目使用该原文时恢复为正常条目，标记为fuzzy
+///
+/// 条目保持语言包中的顺序，头部沿用语言包的头部，`POT-Creation-Date` 改为基础目录中的值
+pub fn update_catalog(mod_catalog: &Catalog, base: &Catalog, options: &UpdateOptions) -> (Catalog, UpdateReport) {
+    let base_entries: HashMap<MessageKey, &Entry> = base.entries.iter()
+        .filter(|e| !e.obsolete)
+        .map(|e| (e.key(), e))
+        .collect();
+    let nplurals = mod_catalog.nplurals();
+
+    let mut updated = Catalog::new();
+    updated.header = mod_catalog.header.clone();
+    updated.header_comments = mod_catalog.header_comments.clone();
+    if let Some(date) = base.header.get(POT_CREATION_DATE) {
+        updated.header.set(POT_CREATION_DATE, date);
+    }
+
+    // 已被语言包中的条目使用的新原文
+    let mut taken: HashSet<MessageKey> = mod_catalog.entries.iter()
+        .filter(|e| !e.obsolete && base_entries.contains_key(&e.key()))
+        .map(Entry::key)
+        .collect();
+
+    let mut report = UpdateReport::default();
+    let mut missing = Vec::new(); // 原文已不存在的正常条目在输出中的位置
+    for entry in &mod_catalog.entries {
+        let key = entry.key();
+        match base_entries.get(&key) {
+            Some(base_entry) if !entry.obsolete => {
+                report.kept += 1;
+                updated.entries.push(carry_over(entry, base_entry, nplurals));
+            }
+            Some(base_entry) if taken.insert(key.clone()) => {
+                // 与 msgmerge 相同，恢复的过时条目需要译者重新确认
+                report.kept += 1;
+                let mut resurrected = carry_over(entry, base_entry, nplurals);
+                mark_fuzzy(&mut resurrected);
+                updated.entries.push(resurrected);
+            }
+            _ => {
+                if !entry.obsolete {
+                    missing.push(updated.entries.len());
+                }
+                updated.entries.push(entry.clone());
+            }
+        }
+    }
+
+    let mut matches = if options.fuzzy_matching {
+        find_matches(&updated.entries, &missing, base, &taken, options.min_similarity)
+    } else {
+        Vec::new()
+    };
+    // 相似度从高到低分配新原文，相同时语言包中靠前的条目优先
+    matches.sort_by(|(a, sa, _), (b, sb, _)| sb.total_cmp(sa).then(a.cmp(b)));
+    let mut moved: HashMap<usize, &Entry> = HashMap::new();
+    for (position, _, base_entry) in matches {
+        if taken.insert(base_entry.key()) {
+            moved.insert(position, base_entry);
+        }
+    }
+
+    for position in missing {
+        let entry = &mut updated.entries[position];
+        let old_key = entry.key();
+        match moved.get(&position) {
+            Some(base_entry) => {
+                *entry = reword(entry, base_entry, nplurals);
+                report.fuzzy.push((old_key, entry.key()));
+            }
+            None => {
+                entry.obsolete = true;
+                report.obsolete.push(old_key);
+            }
+        }
+    }
+
+    (updated, report)
+}
+
+/// 两个字符串的相似度，0到1: 最长公共子序列长度的两倍除以两者的长度之和，与 msgmerge 的 fstrcmp 类似
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let mut previous = vec![0usize; b.len() + 1];
+    let mut current = vec![0usize; b.len() + 1];
+    for &ca in &a {
+        for (j, &cb) in b.iter().enumerate() {
+            current[j + 1] = if ca == cb { previous[j] + 1 } else { previous[j + 1].max(current[j]) };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    2.0 * previous[b.len()] as f64 / (a.len() + b.len()) as f64
+}
+
+// 并行为原文已不存在的已翻译条目查找最相似的未使用的新原文: (在输出中的位置, 相似度, 新条目)
+fn find_matches<'a>(
+    entries: &[Entry],
+    missing: &[usize],
+    base: &'a Catalog,
+    taken: &HashSet<MessageKey>,
+    min_similarity: f64,
+) -> Vec<(usize, f64, &'a Entry)> {
+    let candidates: Vec<(&'a Entry, usize)> = base.entries.iter()
+        .filter(|e| !e.obsolete && !taken.contains(&e.key()))
+        .map(|e| (e, e.msgid.chars().count()))
+        .collect();
+
+    missing.par_iter()
+        .filter(|&&position| entries[position].is_translated())
+        .filter_map(|&position| {
+            let entry = &entries[position];
+            let len = entry.msgid.chars().count();
+            let mut best: Option<(f64, &'a Entry)> = None;
+            for &(candidate, candidate_len) in &candidates {
+                if candidate.msgctxt != entry.msgctxt {
+                    continue;
+                }
+                // 长度相差太大时相似度不可能达到要求
+                let bound = 2.0 * len.min(candidate_len) as f64 / (len + candidate_len).max(1) as f64;
+                if bound < min_similarity || best.is_some_and(|(score, _)| bound <= score) {
+                    continue;
+                }
+                let score = similarity(&entry.msgid, &candidate.msgid);
+                if score >= min_similarity && best.map_or(true, |(best_score, _)| score > best_score) {
+                    best = Some((score, candidate));
+                }
+            }
+            best.map(|(score, candidate)| (position, score, candidate))
+        })
+        .collect()
+}
+
+// 原文不变的条目: 保留译文和注释，采用基础目录中的复数原文和非空的引用、提取的注释
+fn carry_over(entry: &Entry, base_entry: &Entry, nplurals: usize) -> Entry {
+    let mut updated = entry.clone();
+    updated.obsolete = false;
+    if !base_entry.references.is_empty() {
+        updated.references = base_entry.references.clone();
+    }
+    if !base_entry.extracted_comments.is_empty() {
+        updated.extracted_comments = base_entry.extracted_comments.clone();
+    }
+    if base_entry.msgid_plural != entry.msgid_plural {
+        updated.previous_msgctxt = entry.msgctxt.clone();
+        updated.previous_msgid = Some(entry.msgid.clone());
+        updated.previous_msgid_plural = entry.msgid_plural.clone();
+        updated.msgid_plural = base_entry.msgid_plural.clone();
+        updated.msgstr = plural_msgstr(&entry.msgstr, updated.msgid_plural.is_some(), nplurals);
+        mark_fuzzy(&mut updated);
+    }
+    updated
+}
+
+// 原文被改写的条目: 改用新原文，保留译文和译者注释，标记为fuzzy并把旧原文记入 #|
+fn reword(entry: &Entry, base_entry: &Entry, nplurals: usize) -> Entry {
+    let mut updated = Entry {
+        msgctxt: base_entry.msgctxt.clone(),
+        msgid: base_entry.msgid.clone(),
+        msgid_plural: base_entry.msgid_plural.clone(),
+        msgstr: plural_msgstr(&entry.msgstr, base_entry.msgid_plural.is_some(), nplurals),
+        comments: entry.comments.clone(),
+        extracted_comments: base_entry.extracted_comments.clone(),
+        references: base_entry.references.clone(),
+        flags: entry.flags.clone(),
+        previous_msgctxt: entry.msgctxt.clone(),
+        previous_msgid: Some(entry.msgid.clone()),
+        previous_msgid_plural: entry.msgid_plural.clone(),
+        obsolete: false,
+        source_file: entry.source_file.clone(),
+        line_number: entry.line_number,
+    };
+    mark_fuzzy(&mut updated);
+    updated
+}
+
+// 按新原文是否为复数调整译文: 改为复数时每个复数形式都使用原来的译文，改为单数时只保留第一个
+fn plural_msgstr(msgstr: &[String], plural: bool, nplurals: usize) -> Vec<String> {
+    let first = msgstr.first().cloned().unwrap_or_default();
+    match (plural, msgstr.len()) {
+        (true, 1) => vec![first; nplurals],
+        (false, _) => vec![first],
+        _ => msgstr.to_vec(),
+    }
+}
+
+fn mark_fuzzy(entry: &mut Entry) {
+    if !entry.is_fuzzy() {
+        entry.flags.insert(0, "fuzzy".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::converters::po_parser::{self, ParseMode};
+
+    const HEADER: &str = "msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n\"Plural-Forms: nplurals=3; plural=(n==1 ? 0 : n<5 ? 1 : 2);\\n\"\n\n";
+
+    fn catalog(body: &str) -> Catalog {
+        let text = format!("{}{}", HEADER, body);
+        po_parser::parse_reader(Cursor::new(text.as_bytes()), "test.po", ParseMode::Strict).unwrap().catalog
+    }
+
+    fn find<'a>(catalog: &'a Catalog, msgid: &str) -> &'a Entry {
+        catalog.entries.iter().find(|e| e.msgid == msgid).unwrap()
+    }
+
+    fn key(msgid: &str) -> MessageKey {
+        (None, msgid.to_string())
+    }
+
+    #[test]
+    fn similarity_matches_fstrcmp() {
+        assert_eq!(similarity("", ""), 1.0);
+        assert_eq!(similarity("abc", ""), 0.0);
+        assert_eq!(similarity("Open file", "Open file"), 1.0);
+        // 公共子序列 "Opn fil" 长7，(2*7)/(9+7)
+        assert!((similarity("Open file", "Opn fil") - 14.0 / 16.0).abs() < 1e-12);
+        assert!((similarity("文件", "打开文件") - 4.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reworded_entries_become_fuzzy() {
+        let mod_catalog = catalog("# 译者注释\nmsgid \"Open the file\"\nmsgstr \"打开文件\"\n\nmsgid \"Removed message\"\nmsgstr \"已删除\"\n\nmsgid \"Kept\"\nmsgstr \"保留\"\n");
+        let base = catalog("#: new.c:1\nmsgid \"Open the files\"\nmsgstr \"\"\n\nmsgid \"Kept\"\nmsgstr \"\"\n\nmsgid \"Something else entirely\"\nmsgstr \"\"\n");
+
+        let (updated, report) = update_catalog(&mod_catalog, &base, &UpdateOptions::default());
+        assert_eq!(report.kept, 1);
+        assert_eq!(report.fuzzy, vec![(key("Open the file"), key("Open the files"))]);
+        assert_eq!(report.obsolete, vec![key("Removed message")]);
+
+        let reworded = find(&updated, "Open the files");
+        assert!(reworded.is_fuzzy());
+        assert_eq!(reworded.msgstr(), "打开文件");
+        assert_eq!(reworded.previous_msgid.as_deref(), Some("Open the file"));
+        assert_eq!(reworded.comments, ["译者注释"]);
+        assert_eq!(reworded.references, ["new.c:1"]);
+        assert!(find(&updated, "Removed message").obsolete);
+        assert!(!find(&updated, "Kept").is_fuzzy());
+    }
+
+    #[test]
+    fn fuzzy_matching_respects_options() {
+        let mod_catalog = catalog("msgid \"Open the file\"\nmsgstr \"打开文件\"\n");
+        let base = catalog("msgid \"Open the files\"\nmsgstr \"\"\n");
+
+        let options = UpdateOptions { fuzzy_matching: false, ..Default::default() };
+        let (_, report) = update_catalog(&mod_catalog, &base, &options);
+        assert!(report.fuzzy.is_empty());
+        assert_eq!(report.obsolete, vec![key("Open the file")]);
+
+        let options = UpdateOptions { min_similarity: 0.99, ..Default::default() };
+        let (_, report) = update_catalog(&mod_catalog, &base, &options);
+        assert!(report.fuzzy.is_empty());
+    }
+
+    #[test]
+    fn best_match_takes_each_new_msgid_once() {
+        let mod_catalog = catalog("msgid \"Save file\"\nmsgstr \"保存\"\n\nmsgid \"Save files\"\nmsgstr \"保存多个\"\n");
+        let base = catalog("msgid \"Save files!\"\nmsgstr \"\"\n");
+
+        let (updated, report) = update_catalog(&mod_catalog, &base, &UpdateOptions::default());
+        // 相似度更高的条目得到新原文，另一个标记为过时
+        assert_eq!(report.fuzzy, vec![(key("Save files"), key("Save files!"))]);
+        assert_eq!(report.obsolete, vec![key("Save file")]);
+        assert_eq!(find(&updated, "Save files!").msgstr(), "保存多个");
+    }
+
+    #[test]
+    fn plural_changes_become_fuzzy() {
+        let mod_catalog = catalog("msgid \"%d file\"\nmsgstr \"%d 个文件\"\n");
+        let base = catalog("msgid \"%d file\"\nmsgid_plural \"%d files\"\nmsgstr[0] \"\"\nmsgstr[1] \"\"\n");
+
+        let (updated, _) = update_catalog(&mod_catalog, &base, &UpdateOptions::default());
+        let entry = find(&updated, "%d file");
+        assert!(entry.is_fuzzy());
+        assert_eq!(entry.msgid_plural.as_deref(), Some("%d files"));
+        assert_eq!(entry.msgstr, vec!["%d 个文件"; 3]);
+    }
+
+    #[test]
+    fn resurrected_obsolete_entries_are_fuzzy() {
+        let mod_catalog = catalog("#~ msgid \"Back again\"\n#~ msgstr \"又回来了\"\n\nmsgid \"Live\"\nmsgstr \"正常\"\n\n#~ msgid \"Live\"\n#~ msgstr \"旧的\"\n");
+        let base = catalog("msgid \"Back again\"\nmsgstr \"\"\n\nmsgid \"Live\"\nmsgstr \"\"\n");
+
+        let (updated, report) = update_catalog(&mod_catalog, &base, &UpdateOptions::default());
+        assert_eq!(report.kept, 2);
+        let back = find(&updated, "Back again");
+        assert!(!back.obsolete);
+        assert!(back.is_fuzzy());
+        assert_eq!(back.msgstr(), "又回来了");
+
+        // 已有正常条目使用该原文时，过时条目保持不变
+        let live: Vec<&Entry> = updated.entries.iter().filter(|e| e.msgid == "Live").collect();
+        assert_eq!(live.len(), 2);
+        assert!(!live[0].obsolete && !live[0].is_fuzzy());
+        assert!(live[1].obsolete);
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use super::catalog::{Catalog, Entry};
+use super::error::ConvertError;
+use super::header;
+use super::plural::PluralForms;
+use super::po_merger;
+use super::po_parser::{self, Diagnostic, ParseMode, Severity};
+
+/// 解析并校验PO文件，相当于 `msgfmt --check`
+///
+/// 返回解析时跳过的问题和校验发现的问题，文件无法打开或编码无效时返回错误
+pub fn validate_file(path: &Path) -> Result<Vec<Diagnostic>, ConvertError> {
+    let result = po_parser::parse_file(path, ParseMode::Lenient)?;
+    let file = path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let mut diagnostics = result.diagnostics;
+    diagnostics.extend(validate_catalog(&result.catalog, &file));
+    Ok(diagnostics)
+}
+
+/// 校验翻译目录
+///
+/// 检查头部、重复的 msgctxt+msgid、带格式标记(c-format、python-format、python-brace-format)
+/// 的条目中原文与译文的格式指令是否一致、原文与译文开头和结尾的换行是否一致，
+/// 以及复数译文的数量是否等于 nplurals。过时条目不检查，模糊条目只检查是否重复
+pub fn validate_catalog(catalog: &Catalog, file: &str) -> Vec<Diagnostic> {
+    let mut validator = Validator { file, diagnostics: Vec::new() };
+    validator.check_header(catalog);
+
+    // 重复出现的条目只报告重复，不再做其他检查
+    let duplicates = po_merger::find_duplicates(&catalog.entries);
+    let repeated: HashSet<usize> = duplicates.iter()
+        .flat_map(|group| group.positions[1..].iter().copied())
+        .collect();
+    for diagnostic in po_merger::duplicate_diagnostics(&duplicates) {
+        validator.error(diagnostic.line, diagnostic.message);
+    }
+
+    let nplurals = catalog.nplurals();
+    for (i, entry) in catalog.entries.iter().enumerate() {
+        if entry.obsolete || entry.is_fuzzy() || repeated.contains(&i) {
+            continue;
+        }
+        validator.check_plural_count(entry, nplurals);
+        validator.check_newlines(entry);
+        validator.check_format(entry);
+    }
+
+    // 按行号排列，同一行的问题保持发现的顺序
+    validator.diagnostics.sort_by_key(|d| d.line);
+    validator.diagnostics
+}
+
+/// 是否存在错误级别的问题
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(Diagnostic::is_error)
+}
+
+struct Validator<'a> {
+    file: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, line: usize, severity: Severity, message: String) {
+        self.diagnostics.push(Diagnostic {
+            file: self.file.to_string(),
+            line: line.max(1),
+            column: 1,
+            severity,
+            message,
+        });
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.report(line, Severity::Error, message);
+    }
+
+    fn warning(&mut self, line: usize, message: String) {
+        self.report(line, Severity::Warning, message);
+    }
+
+    // 头部条目没有保存行号，问题都报告在第1行
+    fn check_header(&mut self, catalog: &Catalog) {
+        let header = &catalog.header;
+        if header.is_empty() {
+            self.warning(1, "缺少PO头部".to_string());
+        } else {
+            match header.get(header::CONTENT_TYPE) {
+                None => self.warning(1, "头部缺少 Content-Type 字段".to_string()),
+                Some(_) if header.charset().is_none() => self.warning(1, "头部的 Content-Type 没有声明 charset".to_string()),
+                Some(_) => {}
+            }
+            if header.get(header::CONTENT_TRANSFER_ENCODING).is_none() {
+                self.warning(1, "头部缺少 Content-Transfer-Encoding 字段".to_string());
+            }
+
+            // 模板中的占位值说明头部没有填写
+            for (name, value) in header.fields() {
+                if ["PACKAGE VERSION", "CHARSET", "ENCODING", "YEAR-MO-DA HO:MI+ZONE"].iter().any(|p| value.contains(p)) {
+                    self.warning(1, format!("头部字段 {} 仍为模板中的默认值: {}", name, value));
+                }
+            }
+        }
+
+        let has_plurals = catalog.entries.iter().any(|e| !e.obsolete && e.msgid_plural.is_some());
+        match header.plural_forms() {
+            Some(value) => {
+                if let Err(e) = PluralForms::parse(value) {
+                    self.error(1, e.to_string());
+                }
+            }
+            None if has_plurals => self.error(1, "存在复数条目，但头部缺少 Plural-Forms".to_string()),
+            None => {}
+        }
+    }
+
+    fn check_plural_count(&mut self, entry: &Entry, nplurals: usize) {
+        if entry.msgid_plural.is_some() && entry.is_translated() && entry.msgstr.len() != nplurals {
+            self.error(entry.line_number, format!(
+                "复数译文有 {} 个，而头部的 nplurals 为 {}", entry.msgstr.len(), nplurals
+            ));
+        }
+    }
+
+    // 与 msgfmt 一样要求原文和译文开头、结尾的换行一致
+    fn check_newlines(&mut self, entry: &Entry) {
+        for (index, msgstr) in entry.msgstr.iter().enumerate() {
+            if msgstr.is_empty() {
+                continue;
+            }
+            let msgid = original_for(entry, index);
+            let name = msgstr_name(entry, index);
+            if msgid.starts_with('\n') != msgstr.starts_with('\n') {
+                self.error(entry.line_number, format!("msgid 和 {} 的开头不都是换行符", name));
+            }
+            if msgid.ends_with('\n') != msgstr.ends_with('\n') {
+                self.error(entry.line_number, format!("msgid 和 {} 的结尾不都是换行符", name));
+            }
+        }
+    }
+
+    fn check_format(&mut self, entry: &Entry) {
+        let Some(kind) = FormatKind::of(entry) else {
+            return;
+        };
+
+        let msgid = match kind.parse(&entry.msgid) {
+            Ok(directives) => directives,
+            Err(e) => {
+                self.error(entry.line_number, format!("msgid 不是有效的 {}: {}", kind.flag(), e));
+                return;
+            }
+        };
+        let msgid_plural = match entry.msgid_plural.as_deref().map(|plural| kind.parse(plural)) {
+            Some(Err(e)) => {
+                self.error(entry.line_number, format!("msgid_plural 不是有效的 {}: {}", kind.flag(), e));
+                return;
+            }
+            Some(Ok(directives)) => Some(directives),
+            None => None,
+        };
+
+        for (index, msgstr) in entry.msgstr.iter().enumerate() {
+            if msgstr.is_empty() {
+                continue;
+            }
+            let name = msgstr_name(entry, index);
+            let translated = match kind.parse(msgstr) {
+                Ok(directives) => directives,
+                Err(e) => {
+                    self.error(entry.line_number, format!("{} 不是有效的 {}: {}", name, kind.flag(), e));
+                    continue;
+                }
+            };
+
+            // 复数译文与 msgid_plural 比较，并允许省略指令(例如只有一种复数形式的语言不写数量)
+            let (original, allow_missing) = match &msgid_plural {
+                Some(plural) => (plural, true),
+                None => (&msgid, false),
+            };
+            if let Err(e) = compare(original, &translated, allow_missing) {
+                self.error(entry.line_number, format!("{} 与原文的格式指令不一致: {}", name, e));
+            }
+        }
+    }
+}
+
+// 第 `index` 个译文对应的原文
+fn original_for(entry: &Entry, index: usize) -> &str {
+    match (&entry.msgid_plural, index) {
+        (Some(plural), i) if i > 0 => plural,
+        _ => &entry.msgid,
+    }
+}
+
+fn msgstr_name(entry: &Entry, index: usize) -> String {
+    if entry.msgid_plural.is_some() {
+        format!("msgstr[{}]", index)
+    } else {
+        "msgstr".to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormatKind {
+    C,
+    Python,
+    PythonBrace,
+}
+
+// 格式指令引用的参数: 序号(从0开始)或名称
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Arg {
+    Index(usize),
+    Name(String),
+}
+
+impl std::fmt::Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arg::Index(index) => write!(f, "第 {} 个参数", index + 1),
+            Arg::Name(name) => write!(f, "参数 \"{}\"", name),
+        }
+    }
+}
+
+// 参数 -> 参数类型，类型相同的转换字符归为同一类 (例如 %x 和 %u)
+type Directives = BTreeMap<Arg, char>;
+
+impl FormatKind {
+    // 按条目的标记确定格式类型，带 no-xxx-format 标记时不检查
+    fn of(entry: &Entry) -> Option<Self> {
+        if entry.has_flag("c-format") {
+            Some(FormatKind::C)
+        } else if entry.has_flag("python-format") {
+            Some(FormatKind::Python)
+        } else if entry.has_flag("python-brace-format") {
+            Some(FormatKind::PythonBrace)
+        } else {
+            None
+        }
+    }
+
+    fn flag(self) -> &'static str {
+        match self {
+            FormatKind::C => "c-format",
+            FormatKind::Python => "python-format",
+            FormatKind::PythonBrace => "python-brace-format",
+        }
+    }
+
+    fn parse(self, text: &str) -> Result<Directives, String> {
+        match self {
+            FormatKind::C | FormatKind::Python => parse_printf(text, self),
+            FormatKind::PythonBrace => parse_brace(text),
+        }
+    }
+}
+
+// 解析 printf 风格的格式指令: C 的 %s、%1$d，Python 的 %s、%(name)d
+fn parse_printf(text: &str, kind: FormatKind) -> Result<Directives, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut directives = Directives::new();
+    let mut next_index = 0;
+    let mut positional = false;
+    let mut named = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] != '%' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        if chars.get(i) == Some(&'%') {
+            i += 1;
+            continue;
+        }
+
+        // 参数: Python 的 (name) 或 C 的 n$
+        let mut arg = None;
+        if kind == FormatKind::Python && chars.get(i) == Some(&'(') {
+            let end = chars[i..].iter().position(|&c| c == ')')
+                .ok_or_else(|| format!("第 {} 个字符处的 %( 缺少 )", start + 1))?;
+            arg = Some(Arg::Name(chars[i + 1..i + end].iter().collect()));
+            named = true;
+            i += end + 1;
+        } else if kind == FormatKind::C {
+            let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 && chars.get(i + digits) == Some(&'$') {
+                let number: usize = chars[i..i + digits].iter().collect::<String>().parse().unwrap_or(0);
+                if number == 0 {
+                    return Err(format!("第 {} 个字符处的参数序号无效", start + 1));
+                }
+                arg = Some(Arg::Index(number - 1));
+                positional = true;
+                i += digits + 1;
+            }
+        }
+
+        // 标志、宽度、精度和长度修饰
+        while i < chars.len() && "-+ #0'I".contains(chars[i]) {
+            i += 1;
+        }
+        for part in 0..2 {
+            if part == 1 {
+                if chars.get(i) != Some(&'.') {
+                    break;
+                }
+                i += 1;
+            }
+            if chars.get(i) == Some(&'*') {
+                // * 宽度或精度占用一个整数参数
+                if named {
+                    return Err(format!("第 {} 个字符处的命名参数不能使用 *", start + 1));
+                }
+                directives.insert(Arg::Index(next_index), 'd');
+                next_index += 1;
+                i += 1;
+            } else {
+                while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                    i += 1;
+                }
+            }
+        }
+        while i < chars.len() && "hlLqjzZt".contains(chars[i]) {
+            i += 1;
+        }
+
+        let conversion = chars.get(i)
+            .and_then(|&c| conversion_class(kind, c))
+            .ok_or_else(|| format!("第 {} 个字符处的格式指令不完整或无法识别", start + 1))?;
+        i += 1;
+
+        let arg = arg.unwrap_or_else(|| {
+            next_index += 1;
+            Arg::Index(next_index - 1)
+        });
+        if let Some(previous) = directives.insert(arg.clone(), conversion) {
+            if previous != conversion {
+                return Err(format!("{}的类型前后不一致", arg));
+            }
+        }
+    }
+
+    if kind == FormatKind::C && positional && next_index > 0 {
+        return Err("不能混用带序号和不带序号的参数".to_string());
+    }
+    if kind == FormatKind::Python && named && next_index > 0 {
+        return Err("不能混用命名参数和不命名的参数".to_string());
+    }
+    Ok(directives)
+}
+
+// 转换字符所属的类型，类型相同的参数可以互换
+fn conversion_class(kind: FormatKind, c: char) -> Option<char> {
+    let class = match (kind, c) {
+        (_, 'd' | 'i') => 'd',
+        (FormatKind::C, 'o' | 'u' | 'x' | 'X') => 'u',
+        (FormatKind::Python, 'o' | 'u' | 'x' | 'X') => 'd',
+        (_, 'e' | 'E' | 'f' | 'F' | 'g' | 'G') => 'f',
+        (FormatKind::C, 'a' | 'A') => 'f',
+        (_, 'c') => 'c',
+        (FormatKind::C, 's' | 'p' | 'n' | 'C' | 'S') => c,
+        (FormatKind::Python, 's' | 'r' | 'a') => 's',
+        _ => return None,
+    };
+    Some(class)
+}
+
+// 解析 Python str.format 风格的 {name}、{0}、{}，格式说明部分不比较
+fn parse_brace(text: &str) -> Result<Directives, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut directives = Directives::new();
+    let mut next_index = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '{' if chars.get(i + 1) == Some(&'{') => i += 2,
+            '}' if chars.get(i + 1) == Some(&'}') => i += 2,
+            '{' => {
+                // 格式说明中可以嵌套一层 {}
+                let mut depth = 0;
+                let end = chars[i..].iter().position(|&c| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                }).ok_or_else(|| format!("第 {} 个字符处的 {{ 缺少 }}", i + 1))?;
+                let field: String = chars[i + 1..i + end].iter()
+                    .take_while(|&&c| c != ':' && c != '!')
+                    .collect();
+                let name = field.split(['.', '[']).next().unwrap_or_default();
+                let arg = if name.is_empty() {
+                    next_index += 1;
+                    Arg::Index(next_index - 1)
+                } else if let Ok(index) = name.parse() {
+                    Arg::Index(index)
+                } else {
+                    Arg::Name(name.to_string())
+                };
+                directives.insert(arg, 's');
+                i += end + 1;
+            }
+            '}' => return Err(format!("第 {} 个字符处有多余的 }}", i + 1)),
+            _ => i += 1,
+        }
+    }
+    Ok(directives)
+}
+
+// 比较原文和译文的格式指令: 译文不能引用原文中没有的参数，同一参数的类型必须相同
+fn compare(original: &Directives, translated: &Directives, allow_missing: bool) -> Result<(), String> {
+    for (arg, conversion) in translated {
+        match original.get(arg) {
+            None => return Err(format!("原文中没有{}", arg)),
+            Some(expected) if expected != conversion => return Err(format!("{}的类型与原文不同", arg)),
+            Some(_) => {}
+        }
+    }
+    if !allow_missing {
+        if let Some(arg) = original.keys().find(|arg| !translated.contains_key(*arg)) {
+            return Err(format!("译文中缺少{}", arg));
+        }
+    }
+    Ok(())
+}
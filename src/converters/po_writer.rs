@@ -0,0 +1,423 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use super::catalog::{Catalog, Entry};
+use super::error::ConvertError;
+use super::header::Header;
+use super::po_parser::{self, ParseMode};
+use super::progress::{self, MergeControl};
+
+/// msgcat 默认的行宽
+pub const DEFAULT_WIDTH: usize = 79;
+
+/// PO文件中条目的排列顺序，过时条目总是排在最后
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Key,        // 与 msgcat --sort-output 相同，按 msgid、msgctxt 排序，没有 msgctxt 的条目在前
+    Reference,  // 与 msgcat --sort-by-file 相同，条目内的引用(#:)先按文件名和行号排序，再按第一个引用排序，没有引用的条目在前
+    Input,      // 保持输入顺序
+}
+
+/// PO文件的写出选项
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct WriteOptions {
+    pub width: usize,    // 行宽，与 msgcat 一样在空白和中日韩文字处换行；0 表示只在换行符处拆分
+    pub sort: SortOrder, // 条目顺序
+    pub stable: bool,    // 稳定输出，见 [`WriteOptions::canonical`]
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_WIDTH,
+            sort: SortOrder::Key,
+            stable: false,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// 保持条目的输入顺序，其余选项为默认值
+    pub fn input_order() -> Self {
+        Self {
+            sort: SortOrder::Input,
+            ..Self::default()
+        }
+    }
+
+    /// 实际使用的选项: 稳定输出时忽略行宽和顺序设置，固定使用 msgcat 的默认行宽并按 msgid 排序
+    ///
+    /// 稳定输出适合把PO文件放在git中，相同的输入总是得到逐字节相同的输出:
+    /// 只使用LF换行，去掉注释的行尾空白，内容没有变化时保留已有文件中的时间戳
+    pub fn canonical(&self) -> Self {
+        if self.stable {
+            Self { width: DEFAULT_WIDTH, sort: SortOrder::Key, stable: true }
+        } else {
+            *self
+        }
+    }
+}
+
+/// 将翻译目录写入PO文件
+///
+/// 输出先写入临时文件，失败时不会留下写了一半的文件
+pub fn write_po_file(catalog: &Catalog, output_file: impl AsRef<Path>, options: &WriteOptions) -> Result<(), ConvertError> {
+    write_po_file_with(catalog, output_file, options, &MergeControl::default())
+}
+
+/// 将翻译目录写入PO文件，通过 `control` 报告进度并支持取消
+///
+/// 稳定输出时，如果已有的输出文件与新内容只有时间戳(`POT-Creation-Date`、`PO-Revision-Date`)不同，
+/// 则不改写该文件，重新生成不会产生任何差异
+pub fn write_po_file_with(
+    catalog: &Catalog,
+    output_file: impl AsRef<Path>,
+    options: &WriteOptions,
+    control: &MergeControl,
+) -> Result<(), ConvertError> {
+    let output_file = output_file.as_ref();
+    if !options.stable {
+        return progress::write_output(output_file, control, |output| write_catalog(output, catalog, options));
+    }
+
+    let mut content = Vec::new();
+    write_catalog(&mut content, catalog, options)?;
+    if let Ok(existing) = fs::read(output_file) {
+        if existing == content || unchanged_except_timestamps(catalog, &existing, options)? {
+            return Ok(());
+        }
+    }
+    progress::write_output(output_file, control, |output| output.write_all(&content).map_err(ConvertError::write))
+}
+
+// 用已有文件头部中的时间戳重新生成，结果与已有文件相同时说明内容没有变化
+fn unchanged_except_timestamps(catalog: &Catalog, existing: &[u8], options: &WriteOptions) -> Result<bool, ConvertError> {
+    let previous = match po_parser::parse_reader(Cursor::new(existing), "", ParseMode::Lenient) {
+        Ok(result) => result.catalog.header,
+        Err(_) => return Ok(false),
+    };
+    let header = catalog.header.with_timestamps_from(&previous);
+    if header == catalog.header {
+        return Ok(false);
+    }
+
+    let mut content = Vec::new();
+    write_catalog_with_header(&mut content, catalog, &header, options)?;
+    Ok(content == existing)
+}
+
+/// 将翻译目录以PO格式写入
+///
+/// 所有字符串使用同一套转义和换行规则，解析后与原字符串完全相同
+pub fn write_catalog<W: Write>(output: &mut W, catalog: &Catalog, options: &WriteOptions) -> Result<(), ConvertError> {
+    write_catalog_with_header(output, catalog, &catalog.header, options)
+}
+
+fn write_catalog_with_header<W: Write>(output: &mut W, catalog: &Catalog, header: &Header, options: &WriteOptions) -> Result<(), ConvertError> {
+    let options = options.canonical();
+    let mut writer = PoWriter {
+        output,
+        width: options.width,
+        sort_references: options.sort == SortOrder::Reference,
+        trim_comments: options.stable,
+    };
+
+    // 写入头部信息
+    let mut separate = false;
+    if !header.is_empty() {
+        for comment in &catalog.header_comments {
+            writer.comment("#", comment)?;
+        }
+        writer.string("", "msgid", "")?;
+        writer.string("", "msgstr", &header.to_string())?;
+        separate = true;
+    }
+
+    let mut entries: Vec<&Entry> = catalog.entries.iter().collect();
+    sort_entries(&mut entries, options.sort);
+
+    // 写入所有条目，条目之间以空行分隔，文件末尾没有空行
+    let nplurals = catalog.nplurals();
+    for entry in entries {
+        if separate {
+            writer.blank_line()?;
+        }
+        writer.entry(entry, nplurals)?;
+        separate = true;
+    }
+
+    Ok(())
+}
+
+/// 按指定顺序排列条目，过时条目排在最后；排序是稳定的，`SortOrder::Input` 时其余条目顺序不变
+pub fn sort_entries<T: Borrow<Entry>>(entries: &mut [T], order: SortOrder) {
+    entries.sort_by(|a, b| {
+        let (a, b) = (a.borrow(), b.borrow());
+        a.obsolete.cmp(&b.obsolete).then_with(|| match order {
+            SortOrder::Key => compare_keys(a, b),
+            SortOrder::Reference => compare_references(a, b).then_with(|| compare_keys(a, b)),
+            SortOrder::Input => Ordering::Equal,
+        })
+    });
+}
+
+fn compare_keys(a: &Entry, b: &Entry) -> Ordering {
+    a.msgid.cmp(&b.msgid).then_with(|| a.msgctxt.cmp(&b.msgctxt))
+}
+
+// 与 msgcat 相同，只比较排序后的第一个引用
+fn compare_references(a: &Entry, b: &Entry) -> Ordering {
+    first_reference(a).cmp(&first_reference(b))
+}
+
+fn first_reference(entry: &Entry) -> Option<(&str, u64)> {
+    entry.references.iter().map(|reference| reference_key(reference)).min()
+}
+
+// 按文件名和行号排序引用
+fn sorted_references(references: &[String]) -> Vec<&String> {
+    let mut sorted: Vec<&String> = references.iter().collect();
+    sorted.sort_by_key(|reference| reference_key(reference));
+    sorted
+}
+
+// 引用的排序键，文件名相同时按行号的数值比较
+fn reference_key(reference: &str) -> (&str, u64) {
+    match reference.rsplit_once(':') {
+        Some((file, line)) => match line.parse() {
+            Ok(line) => (file, line),
+            Err(_) => (reference, 0),
+        },
+        None => (reference, 0),
+    }
+}
+
+struct PoWriter<'a, W> {
+    output: &'a mut W,
+    width: usize,
+    sort_references: bool, // 写出前排序条目内的引用
+    trim_comments: bool,   // 去掉注释的行尾空白(包括CRLF换行留下的 \r)
+}
+
+impl<W: Write> PoWriter<'_, W> {
+    fn line(&mut self, line: &str) -> Result<(), ConvertError> {
+        writeln!(self.output, "{}", line).map_err(ConvertError::write)
+    }
+
+    fn blank_line(&mut self) -> Result<(), ConvertError> {
+        writeln!(self.output).map_err(ConvertError::write)
+    }
+
+    // 写入单个条目，注释按 gettext 的顺序输出
+    fn entry(&mut self, entry: &Entry, nplurals: usize) -> Result<(), ConvertError> {
+        for comment in &entry.comments {
+            self.comment("#", comment)?;
+        }
+        for comment in &entry.extracted_comments {
+            self.comment("#.", comment)?;
+        }
+        self.references(&entry.references)?;
+        if !entry.flags.is_empty() {
+            self.line(&format!("#, {}", entry.flags.join(", ")))?;
+        }
+
+        // 过时条目的每一行都带有 "#~" 前缀
+        let (prefix, previous_prefix) = if entry.obsolete { ("#~ ", "#~| ") } else { ("", "#| ") };
+
+        // 之前的原文
+        if let Some(ref ctx) = entry.previous_msgctxt {
+            self.string(previous_prefix, "msgctxt", ctx)?;
+        }
+        if let Some(ref msgid) = entry.previous_msgid {
+            self.string(previous_prefix, "msgid", msgid)?;
+        }
+        if let Some(ref plural) = entry.previous_msgid_plural {
+            self.string(previous_prefix, "msgid_plural", plural)?;
+        }
+
+        if let Some(ref ctx) = entry.msgctxt {
+            self.string(prefix, "msgctxt", ctx)?;
+        }
+        self.string(prefix, "msgid", &entry.msgid)?;
+
+        // 复数条目写入 msgid_plural 和 msgstr[n]，译文数量补齐到 nplurals
+        if let Some(ref plural) = entry.msgid_plural {
+            self.string(prefix, "msgid_plural", plural)?;
+            let nplurals = nplurals.max(entry.msgstr.len());
+            for index in 0..nplurals {
+                let msgstr = entry.msgstr.get(index).map(|s| s.as_str()).unwrap_or("");
+                self.string(prefix, &format!("msgstr[{}]", index), msgstr)?;
+            }
+        } else {
+            self.string(prefix, "msgstr", entry.msgstr())?;
+        }
+
+        Ok(())
+    }
+
+    // 写入一行注释，空注释只写标记本身
+    fn comment(&mut self, marker: &str, text: &str) -> Result<(), ConvertError> {
+        let text = if self.trim_comments { text.trim_end() } else { text };
+        if text.is_empty() {
+            self.line(marker)
+        } else {
+            self.line(&format!("{} {}", marker, text))
+        }
+    }
+
+    // 写入源码位置引用，超过行宽时与 msgcat 一样分成多行(不折行时也按默认行宽)
+    fn references(&mut self, references: &[String]) -> Result<(), ConvertError> {
+        let references = if self.sort_references {
+            sorted_references(references)
+        } else {
+            references.iter().collect()
+        };
+
+        let width = if self.width == 0 { DEFAULT_WIDTH } else { self.width };
+        let mut line = String::new();
+        for reference in references {
+            if !line.is_empty() && text_width(&line) + 1 + text_width(reference) > width {
+                self.line(&line)?;
+                line.clear();
+            }
+            if line.is_empty() {
+                line.push_str("#:");
+            }
+            line.push(' ');
+            line.push_str(reference);
+        }
+        if !line.is_empty() {
+            self.line(&line)?;
+        }
+        Ok(())
+    }
+
+    // 写入带关键字的字符串，每一行都带有 `prefix` (例如 "#~ ")
+    //
+    // 与 msgcat 相同: 只在末尾有换行符且能放在一行内(或者根本无法折行)时写在关键字之后，
+    // 否则关键字后写空字符串，内容在换行符后拆分，超过行宽的部分再折行
+    fn string(&mut self, prefix: &str, key: &str, value: &str) -> Result<(), ConvertError> {
+        let escaped = escape_po_string(value);
+        let multiline = value.strip_suffix('\n').unwrap_or(value).contains('\n');
+        let fits = self.width == 0 || text_width(prefix) + text_width(key) + text_width(&escaped) + 3 <= self.width;
+        if !multiline && (fits || !has_break(value)) {
+            return self.line(&format!("{}{} \"{}\"", prefix, key, escaped));
+        }
+
+        self.line(&format!("{}{} \"\"", prefix, key))?;
+        let available = if self.width == 0 { 0 } else { self.width.saturating_sub(text_width(prefix) + 2).max(1) };
+        for line in wrap(value, available) {
+            self.line(&format!("{}\"{}\"", prefix, line))?;
+        }
+        Ok(())
+    }
+}
+
+// 将字符串拆分为多行，返回已转义的各行内容(不含引号)
+//
+// 先在每个换行符之后拆分，每段再在 `width` 内尽量多放内容，只在空格之后或中日韩文字前后断开，
+// 找不到断点时允许超出行宽；`width` 为0时不折行
+fn wrap(value: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for segment in value.split_inclusive('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+        let mut last_break: Option<(usize, usize)> = None; // 断点在 line 中的字节位置及之前内容的宽度
+        let mut previous: Option<char> = None;
+
+        for c in segment.chars() {
+            if previous.is_some_and(|p| can_break_between(p, c)) {
+                last_break = Some((line.len(), line_width));
+            }
+
+            let escaped = escape_char(c);
+            let char_width = text_width(&escaped);
+            if width > 0 && line_width + char_width > width {
+                if let Some((at, at_width)) = last_break.take() {
+                    if at > 0 {
+                        let rest = line.split_off(at);
+                        lines.push(std::mem::replace(&mut line, rest));
+                        line_width -= at_width;
+                    }
+                }
+            }
+
+            line.push_str(&escaped);
+            line_width += char_width;
+            previous = Some(c);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// 字符串中是否有可以折行的位置
+fn has_break(value: &str) -> bool {
+    value.chars().zip(value.chars().skip(1)).any(|(before, after)| can_break_between(before, after))
+}
+
+// 两个字符之间是否可以折行
+fn can_break_between(before: char, after: char) -> bool {
+    const NO_LINE_START: &str = ",.;:!?)]}>'\"%，。、；：！？）】」』》〉”’…～";
+    const NO_LINE_END: &str = "([{<（【「『《〈“‘";
+
+    if after == ' ' || NO_LINE_START.contains(after) || NO_LINE_END.contains(before) {
+        false
+    } else {
+        before == ' ' || is_wide(before) || is_wide(after)
+    }
+}
+
+/// 转义PO字符串，控制字符全部转义，解析后与原字符串完全相同
+pub fn escape_po_string(s: &str) -> String {
+    s.chars().map(escape_char).collect()
+}
+
+fn escape_char(c: char) -> String {
+    match c {
+        '\\' => "\\\\".to_string(),
+        '"' => "\\\"".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        '\x07' => "\\a".to_string(),
+        '\x08' => "\\b".to_string(),
+        '\x0B' => "\\v".to_string(),
+        '\x0C' => "\\f".to_string(),
+        c if c.is_ascii_control() => format!("\\{:03o}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+// 文本的显示宽度，中日韩文字等宽字符占两列
+fn text_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+fn char_width(c: char) -> usize {
+    if is_wide(c) { 2 } else { 1 }
+}
+
+// 是否为东亚宽字符(中日韩文字、全角符号等)
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD)
+}
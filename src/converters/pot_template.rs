@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use chrono::Local;
+
+use super::catalog::{Catalog, Entry};
+use super::error::ConvertError;
+use super::header::{self, Header};
+use super::mo_converter::MoConverter;
+use super::po_converter;
+use super::po_writer::{self, WriteOptions};
+
+/// 生成模板时的条目过滤条件，两个条件都设置时条目需同时满足
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TemplateFilter {
+    pub untranslated_only: bool, // 只包含基础目录中未翻译或译文与原文相同的条目；编译的MO文件不含未翻译的条目，只剩译文与原文相同的条目
+    pub pattern: String,         // 原文或上下文匹配的模式，不区分大小写，支持 * 和 ?，不含通配符时按包含匹配；为空时不过滤
+}
+
+impl TemplateFilter {
+    /// 条目是否包含在模板中
+    pub fn matches(&self, entry: &Entry) -> bool {
+        if self.untranslated_only && entry.is_translated() && !po_converter::is_identical(entry) {
+            return false;
+        }
+        let pattern = self.pattern.trim().to_lowercase();
+        if pattern.is_empty() {
+            return true;
+        }
+        let pattern = if pattern.contains(['*', '?']) { pattern } else { format!("*{}*", pattern) };
+        let pattern: Vec<char> = pattern.chars().collect();
+        std::iter::once(entry.msgid.as_str())
+            .chain(entry.msgctxt.as_deref())
+            .any(|text| wildcard_match(&pattern, &text.to_lowercase().chars().collect::<Vec<_>>()))
+    }
+}
+
+/// 从基础MO文件生成POT模板，返回写入的条目数
+///
+/// MO文件中只有已翻译的条目，`untranslated_only` 只会选出译文与原文相同的条目
+pub fn write_pot_file(base_mo: &Path, output: &Path, filter: &TemplateFilter, options: &WriteOptions) -> Result<usize, ConvertError> {
+    let template = template_from_catalog(&MoConverter::read_mo_file(base_mo)?, filter);
+    po_writer::write_po_file(&template, output, options)?;
+    Ok(template.len())
+}
+
+/// 由翻译目录生成模板: 保留 msgctxt、msgid、复数原文、引用和提取的注释，清空译文，去掉fuzzy标记
+///
+/// 头部按 xgettext 的模板头部生成，`Project-Id-Version` 沿用基础目录中的值
+pub fn template_from_catalog(base: &Catalog, filter: &TemplateFilter) -> Catalog {
+    let mut template = Catalog::new();
+    template.header = template_header(&base.header);
+    template.entries = base.entries.iter()
+        .filter(|entry| !entry.obsolete && filter.matches(entry))
+        .map(|entry| Entry {
+            msgctxt: entry.msgctxt.clone(),
+            msgid: entry.msgid.clone(),
+            msgid_plural: entry.msgid_plural.clone(),
+            // 与 xgettext 一样，复数条目写出 msgstr[0] 和 msgstr[1]
+            msgstr: vec![String::new(); if entry.msgid_plural.is_some() { 2 } else { 1 }],
+            extracted_comments: entry.extracted_comments.clone(),
+            references: entry.references.clone(),
+            flags: entry.flags.iter().filter(|flag| *flag != "fuzzy").cloned().collect(),
+            ..Default::default()
+        })
+        .collect();
+    template
+}
+
+// 模板的头部，占位值与 xgettext 相同
+fn template_header(base: &Header) -> Header {
+    let now = Local::now().format("%Y-%m-%d %H:%M%z").to_string();
+
+    let mut header = Header::new();
+    header.set(header::PROJECT_ID_VERSION, base.project_id_version().unwrap_or("PACKAGE VERSION"));
+    header.set(header::POT_CREATION_DATE, &now);
+    header.set(header::PO_REVISION_DATE, "YEAR-MO-DA HO:MI+ZONE");
+    header.set(header::LAST_TRANSLATOR, "FULL NAME <EMAIL@ADDRESS>");
+    header.set("Language-Team", "LANGUAGE <LL@li.org>");
+    header.set(header::MIME_VERSION, "1.0");
+    header.set(header::CONTENT_TYPE, "text/plain; charset=UTF-8");
+    header.set(header::CONTENT_TRANSFER_ENCODING, "8bit");
+    header.set(header::GENERATOR, header::GENERATOR_NAME);
+    header
+}
+
+// 通配符匹配: * 匹配任意个字符，? 匹配一个字符
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None; // 最近一个 * 的位置和它当前匹配到的文本位置
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(msgctxt: Option<&str>, msgid: &str, msgstr: &str) -> Entry {
+        Entry::new(msgctxt.map(str::to_string), msgid.to_string(), msgstr.to_string())
+    }
+
+    #[test]
+    fn filters_entries() {
+        let mut base = Catalog::new();
+        base.entries = vec![
+            entry(None, "Open File", "打开文件"),
+            entry(None, "OK", "OK"),
+            entry(None, "Untranslated", ""),
+            entry(Some("Menu"), "Save", "保存"),
+        ];
+        let msgids = |filter: &TemplateFilter| -> Vec<String> {
+            template_from_catalog(&base, filter).entries.into_iter().map(|e| e.msgid).collect()
+        };
+
+        assert_eq!(msgids(&TemplateFilter::default()).len(), 4);
+        assert_eq!(msgids(&TemplateFilter { untranslated_only: true, ..Default::default() }), ["OK", "Untranslated"]);
+        assert_eq!(msgids(&TemplateFilter { pattern: "file".to_string(), ..Default::default() }), ["Open File"]);
+        assert_eq!(msgids(&TemplateFilter { pattern: "me?u".to_string(), ..Default::default() }), ["Save"]);
+        assert_eq!(msgids(&TemplateFilter { pattern: "o*".to_string(), ..Default::default() }), ["Open File", "OK"]);
+    }
+
+    #[test]
+    fn template_clears_translations() {
+        let mut base = Catalog::new();
+        let mut plural = entry(None, "%d file", "%d 个文件");
+        plural.msgid_plural = Some("%d files".to_string());
+        plural.flags = vec!["fuzzy".to_string(), "c-format".to_string()];
+        base.entries = vec![plural];
+
+        let template = template_from_catalog(&base, &TemplateFilter::default());
+        assert_eq!(template.entries[0].msgstr, ["", ""]);
+        assert_eq!(template.entries[0].flags, ["c-format"]);
+        assert_eq!(template.header.get(header::LANGUAGE), None);
+        assert_eq!(template.header.get(header::CONTENT_TYPE), Some("text/plain; charset=UTF-8"));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::error::{ConvertError, IoOp};
+
+/// 合并过程的进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeProgress {
+    FilesParsed { done: usize, total: usize },     // 已解析的输入文件数
+    EntriesResolved { done: usize, total: usize }, // 已处理冲突的条目数
+    BytesWritten(u64),                             // 已写入输出文件的字节数
+}
+
+/// 取消标志，可在其他线程中调用 [`CancelToken::cancel`] 中止正在进行的合并
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 合并过程的进度回调和取消标志，默认不报告进度且不可取消
+#[derive(Clone, Default)]
+pub struct MergeControl {
+    progress: Option<Arc<dyn Fn(MergeProgress) + Send + Sync>>,
+    cancel: CancelToken,
+}
+
+impl MergeControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置进度回调，回调可能在多个工作线程中同时调用
+    pub fn with_progress(mut self, progress: impl Fn(MergeProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn report(&self, progress: MergeProgress) {
+        if let Some(callback) = &self.progress {
+            callback(progress);
+        }
+    }
+
+    /// 已取消时返回 [`ConvertError::Cancelled`]
+    pub fn check(&self) -> Result<(), ConvertError> {
+        if self.cancel.is_cancelled() {
+            Err(ConvertError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// 统计写入字节数的输出流，每次写入时报告进度并检查是否已取消
+pub struct ProgressWriter<'a, W> {
+    inner: W,
+    control: &'a MergeControl,
+    written: u64,
+}
+
+impl<'a, W: Write> ProgressWriter<'a, W> {
+    pub fn new(inner: W, control: &'a MergeControl) -> Self {
+        Self { inner, control, written: 0 }
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.control.cancel.is_cancelled() {
+            return Err(io::Error::other(ConvertError::Cancelled));
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        self.control.report(MergeProgress::BytesWritten(self.written));
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 先写入同目录下的临时文件，成功后再替换目标文件
+///
+/// 写入失败或被取消时删除临时文件，目标文件保持原样，不会留下写了一半的输出
+pub fn write_output<F>(path: &Path, control: &MergeControl, write: F) -> Result<(), ConvertError>
+where
+    F: FnOnce(&mut BufWriter<ProgressWriter<'_, File>>) -> Result<(), ConvertError>,
+{
+    let temp_path = temp_path(path);
+    let result = write_temp(&temp_path, control, write)
+        .and_then(|_| fs::rename(&temp_path, path).map_err(|e| ConvertError::io(IoOp::Rename, path, e)));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_temp<F>(temp_path: &Path, control: &MergeControl, write: F) -> Result<(), ConvertError>
+where
+    F: FnOnce(&mut BufWriter<ProgressWriter<'_, File>>) -> Result<(), ConvertError>,
+{
+    control.check()?;
+    let file = File::create(temp_path).map_err(|e| ConvertError::io(IoOp::Create, temp_path, e))?;
+    let mut writer = BufWriter::new(ProgressWriter::new(file, control));
+    let result = write(&mut writer)
+        .and_then(|_| writer.flush().map_err(|e| ConvertError::io(IoOp::Write, temp_path, e)));
+    // 写入中途取消时上层得到的是I/O错误，这里统一返回取消
+    control.check()?;
+    result
+}
+
+// 临时文件: 目标文件名后加 .part
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+
+use super::catalog::{Entry, MessageKey};
+use super::error::ConvertError;
+use super::header::Header;
+use super::mo_converter::MoConverter;
+use super::mo_file::MoFile;
+use super::po_converter::{CompileOptions, CompileReport, PoConverter};
+use super::po_merger;
+use super::po_parser::{self, Diagnostic, ParseMode, Severity};
+use super::po_writer::{self, SortOrder, WriteOptions};
+
+/// 往返校验的结果
+#[derive(Clone, Debug, Default)]
+pub struct VerifyReport {
+    pub compile: CompileReport,       // 编译时写入和跳过的条目数
+    pub differences: Vec<Diagnostic>, // 往返后与原文件不同的地方，行号为原PO文件中的行号
+}
+
+impl VerifyReport {
+    /// 往返后所有写入MO文件的条目都没有改变
+    pub fn is_lossless(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// 校验PO文件经过编译和反编译后内容不变
+///
+/// 按 `options` 将PO文件编译为MO，再将MO反编译为PO文本并重新解析，逐条比较。
+/// 编译时按选项跳过的条目不参与比较，复数译文按 nplurals 补齐后比较，
+/// 其余条目的原文、复数原文和译文必须逐字节相同，头部也必须相同
+pub fn verify_po_roundtrip(input: &Path, options: &CompileOptions) -> Result<VerifyReport, ConvertError> {
+    let file = input.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let mut catalog = po_merger::parse_po_file(input)?;
+    catalog.entries = po_merger::apply_duplicate_policy(catalog.entries, options.duplicate_policy)?;
+
+    // 在内存中编译并反编译
+    let mut mo_bytes = Vec::new();
+    let compile = PoConverter::write_mo_file(&mut mo_bytes, &catalog, options)?;
+    let mo = MoFile::from_bytes(mo_bytes)?;
+    let mut po_bytes = Vec::new();
+    MoConverter::write_po(&mut po_bytes, &mo, &WriteOptions::default())?;
+    let decompiled = po_parser::parse_reader(Cursor::new(po_bytes), &file, ParseMode::Strict)?.catalog;
+
+    let mut differences = Vec::new();
+    let mut report = |line: usize, message: String| {
+        differences.push(Diagnostic { file: file.clone(), line: line.max(1), column: 1, severity: Severity::Error, message });
+    };
+
+    // 头部: 编译时没有头部会写入最小头部
+    let header = if catalog.header.is_empty() { Header::minimal() } else { catalog.header.clone() };
+    if header.to_string() != decompiled.header.to_string() {
+        report(1, format!("头部不同: 原为 {:?}，往返后为 {:?}", header.to_string(), decompiled.header.to_string()));
+    }
+
+    let mut actual: HashMap<MessageKey, Entry> = decompiled.entries.into_iter()
+        .map(|entry| (entry.key(), entry))
+        .collect();
+    let nplurals = catalog.nplurals();
+    for entry in catalog.entries.iter().filter(|e| options.compiles(e)) {
+        let Some(result) = actual.remove(&entry.key()) else {
+            report(entry.line_number, format!("条目 {:?} 往返后丢失", entry.msgid));
+            continue;
+        };
+
+        let mut expected = entry.msgstr.clone();
+        if entry.msgid_plural.is_some() && expected.len() < nplurals {
+            expected.resize(nplurals, String::new());
+        }
+        if result.msgid_plural != entry.msgid_plural {
+            report(entry.line_number, format!("条目 {:?} 的复数原文不同: 原为 {:?}，往返后为 {:?}", entry.msgid, entry.msgid_plural, result.msgid_plural));
+        } else if result.msgstr != expected {
+            report(entry.line_number, format!("条目 {:?} 的译文不同: 原为 {:?}，往返后为 {:?}", entry.msgid, expected, result.msgstr));
+        }
+    }
+
+    // 剩下的是往返后多出来的条目，按原文排序以便输出稳定
+    let mut extra: Vec<Entry> = actual.into_values().collect();
+    po_writer::sort_entries(&mut extra, SortOrder::Key);
+    for entry in extra {
+        report(1, format!("往返后多出了条目 {:?}", entry.msgid));
+    }
+
+    Ok(VerifyReport { compile, differences })
+}
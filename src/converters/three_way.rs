@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::catalog::{Catalog, Entry, MessageKey};
+use super::error::ConvertError;
+use super::po_merger::{self, DuplicatePolicy};
+use super::po_writer::{self, WriteOptions};
+
+/// 三方合并的结果
+#[derive(Clone, Debug, Default)]
+pub struct ThreeWayReport {
+    pub conflicts: Vec<MessageKey>, // 双方都修改了译文的条目，已标记为fuzzy并在译者注释中列出双方的译文
+}
+
+impl ThreeWayReport {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+/// 三方合并PO文件，结果写入 `output`
+///
+/// 可作为git的合并驱动使用，`base`、`ours`、`theirs` 分别对应 `%O`、`%A`、`%B`，
+/// 输出写回 `%A`。每个文件中重复的条目以最后一个为准
+pub fn merge_po_files(base: &Path, ours: &Path, theirs: &Path, output: &Path, options: &WriteOptions) -> Result<ThreeWayReport, ConvertError> {
+    let parse = |path: &Path| -> Result<Catalog, ConvertError> {
+        let mut catalog = po_merger::parse_po_file(path)?;
+        catalog.entries = po_merger::apply_duplicate_policy(catalog.entries, DuplicatePolicy::LastWins)?;
+        Ok(catalog)
+    };
+    let (merged, report) = merge_catalogs(&parse(base)?, &parse(ours)?, &parse(theirs)?);
+    po_writer::write_po_file(&merged, output, options)?;
+    Ok(report)
+}
+
+/// 以 `base` 为共同祖先逐条合并 `ours` 和 `theirs`，条目按 msgctxt+msgid 对应
+///
+/// 过时条目与正常条目按同一个键对应，过时标记作为条目的一个字段合并，输出中每个键只有一个条目；
+/// 同一文件中有同键的正常条目时忽略过时条目。
+/// 只有一方修改(或删除)的条目采用修改后的版本；双方都修改时逐个字段合并，
+/// 标记和源码位置引用按集合合并，其余字段双方修改不同时以 `ours` 为准；
+/// 只有译文也无法合并时才算冲突: 保留 `ours` 的译文，标记为fuzzy，并在译者注释中写出双方的译文。
+/// 一方删除而另一方修改的条目同样作为冲突保留。
+/// 头部只有 `ours` 未修改时采用 `theirs` 的头部。
+/// 条目按 `ours` 中的顺序排列，只在 `theirs` 中的条目插在它在 `theirs` 中的前一个条目之后
+pub fn merge_catalogs(base: &Catalog, ours: &Catalog, theirs: &Catalog) -> (Catalog, ThreeWayReport) {
+    let base_entries = entry_map(base);
+    let our_entries = entry_map(ours);
+    let their_entries = entry_map(theirs);
+
+    let mut merged = Catalog::new();
+    let header_unchanged = ours.header == base.header && ours.header_comments == base.header_comments;
+    let header_source = if header_unchanged { theirs } else { ours };
+    merged.header = header_source.header.clone();
+    merged.header_comments = header_source.header_comments.clone();
+
+    let mut report = ThreeWayReport::default();
+    for key in merged_order(ours, theirs, &our_entries) {
+        let base_entry = base_entries.get(&key).copied();
+        let our_entry = our_entries.get(&key).copied();
+        let their_entry = their_entries.get(&key).copied();
+        match merge_entry(base_entry, our_entry, their_entry) {
+            Merged::Resolved(Some(entry)) => merged.entries.push(entry),
+            Merged::Resolved(None) => {}
+            Merged::Conflict(entry) => {
+                report.conflicts.push(entry.key());
+                merged.entries.push(entry);
+            }
+        }
+    }
+
+    (merged, report)
+}
+
+// 按键索引条目，同键的正常条目优先于过时条目
+fn entry_map(catalog: &Catalog) -> HashMap<MessageKey, &Entry> {
+    let mut entries: HashMap<MessageKey, &Entry> = HashMap::with_capacity(catalog.entries.len());
+    for entry in &catalog.entries {
+        let slot = entries.entry(entry.key()).or_insert(entry);
+        if slot.obsolete {
+            *slot = entry;
+        }
+    }
+    entries
+}
+
+// 输出中条目的顺序，每个键只出现一次
+fn merged_order(ours: &Catalog, theirs: &Catalog, our_entries: &HashMap<MessageKey, &Entry>) -> Vec<MessageKey> {
+    // 只在 theirs 中的条目，按插入位置(theirs 中前一个也在 ours 中的条目)分组
+    let mut following: HashMap<Option<MessageKey>, Vec<MessageKey>> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut anchor = None;
+    for entry in &theirs.entries {
+        let key = entry.key();
+        if our_entries.contains_key(&key) {
+            anchor = Some(key);
+        } else if seen.insert(key.clone()) {
+            following.entry(anchor.clone()).or_default().push(key);
+        }
+    }
+
+    let mut order = following.remove(&None).unwrap_or_default();
+    let mut seen = HashSet::new();
+    for entry in &ours.entries {
+        let key = entry.key();
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        let inserted = following.remove(&Some(key.clone()));
+        order.push(key);
+        order.extend(inserted.into_iter().flatten());
+    }
+    order
+}
+
+enum Merged {
+    Resolved(Option<Entry>), // None 表示条目被删除
+    Conflict(Entry),
+}
+
+fn merge_entry(base: Option<&Entry>, ours: Option<&Entry>, theirs: Option<&Entry>) -> Merged {
+    if same_entry(ours, theirs) || same_entry(theirs, base) {
+        return Merged::Resolved(ours.cloned());
+    }
+    if same_entry(ours, base) {
+        return Merged::Resolved(theirs.cloned());
+    }
+
+    let (Some(our_entry), Some(their_entry)) = (ours, theirs) else {
+        // 一方删除，另一方修改
+        let kept = ours.or(theirs).cloned().unwrap_or_default();
+        return Merged::Conflict(mark_conflict(kept, ours, theirs));
+    };
+
+    let mut entry = our_entry.clone();
+    entry.msgid_plural = merge_field(base, our_entry, their_entry, |e| &e.msgid_plural);
+    entry.comments = merge_field(base, our_entry, their_entry, |e| &e.comments);
+    entry.extracted_comments = merge_field(base, our_entry, their_entry, |e| &e.extracted_comments);
+    entry.previous_msgctxt = merge_field(base, our_entry, their_entry, |e| &e.previous_msgctxt);
+    entry.previous_msgid = merge_field(base, our_entry, their_entry, |e| &e.previous_msgid);
+    entry.previous_msgid_plural = merge_field(base, our_entry, their_entry, |e| &e.previous_msgid_plural);
+    entry.obsolete = merge_field(base, our_entry, their_entry, |e| &e.obsolete);
+    entry.references = merge_sets(base.map(|b| &b.references), &our_entry.references, &their_entry.references);
+    entry.flags = merge_sets(base.map(|b| &b.flags), &our_entry.flags, &their_entry.flags);
+
+    match pick(base.map(|b| &b.msgstr), &our_entry.msgstr, &their_entry.msgstr) {
+        Some(msgstr) => {
+            entry.msgstr = msgstr;
+            Merged::Resolved(Some(entry))
+        }
+        None => Merged::Conflict(mark_conflict(entry, ours, theirs)),
+    }
+}
+
+// 比较条目内容，不比较来源文件和行号
+fn same_entry(a: Option<&Entry>, b: Option<&Entry>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.msgctxt == b.msgctxt
+                && a.msgid == b.msgid
+                && a.msgid_plural == b.msgid_plural
+                && a.msgstr == b.msgstr
+                && a.comments == b.comments
+                && a.extracted_comments == b.extracted_comments
+                && a.references == b.references
+                && a.flags == b.flags
+                && a.previous_msgctxt == b.previous_msgctxt
+                && a.previous_msgid == b.previous_msgid
+                && a.previous_msgid_plural == b.previous_msgid_plural
+                && a.obsolete == b.obsolete
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// 单个字段的三方合并，双方修改不同时返回None
+fn pick<T: PartialEq + Clone>(base: Option<&T>, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || base == Some(theirs) {
+        Some(ours.clone())
+    } else if base == Some(ours) {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+// 合并条目的一个字段，双方修改不同时以 ours 为准
+fn merge_field<T: PartialEq + Clone>(base: Option<&Entry>, ours: &Entry, theirs: &Entry, field: fn(&Entry) -> &T) -> T {
+    pick(base.map(field), field(ours), field(theirs)).unwrap_or_else(|| field(ours).clone())
+}
+
+// 按集合合并列表: 每一项是否保留由三方合并决定，不会冲突；顺序以 ours 为准，theirs 新增的项排在后面
+fn merge_sets(base: Option<&Vec<String>>, ours: &[String], theirs: &[String]) -> Vec<String> {
+    let in_base = |item: &String| base.is_some_and(|base| base.contains(item));
+    let mut merged: Vec<String> = ours.iter()
+        .filter(|item| theirs.contains(item) || !in_base(item))
+        .cloned()
+        .collect();
+    for item in theirs {
+        if !ours.contains(item) && !in_base(item) {
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+// 冲突的条目标记为fuzzy，双方的译文写在译者注释中
+fn mark_conflict(mut entry: Entry, ours: Option<&Entry>, theirs: Option<&Entry>) -> Entry {
+    entry.comments.extend(candidate_comments("ours", ours));
+    entry.comments.extend(candidate_comments("theirs", theirs));
+    if !entry.is_fuzzy() {
+        entry.flags.insert(0, "fuzzy".to_string());
+    }
+    entry
+}
+
+fn candidate_comments(side: &str, entry: Option<&Entry>) -> Vec<String> {
+    match entry {
+        None => vec![format!("合并冲突 {}: 已删除此条目", side)],
+        Some(entry) if entry.msgid_plural.is_some() => entry.msgstr.iter()
+            .enumerate()
+            .map(|(index, msgstr)| format!("合并冲突 {} msgstr[{}]: \"{}\"", side, index, po_writer::escape_po_string(msgstr)))
+            .collect(),
+        Some(entry) => vec![format!("合并冲突 {}: \"{}\"", side, po_writer::escape_po_string(entry.msgstr()))],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::converters::po_parser::{self, ParseMode};
+
+    fn catalog(body: &str) -> Catalog {
+        let text = format!("msgid \"\"\nmsgstr \"Content-Type: text/plain; charset=UTF-8\\n\"\n\n{}", body);
+        po_parser::parse_reader(Cursor::new(text.as_bytes()), "test.po", ParseMode::Strict).unwrap().catalog
+    }
+
+    fn find<'a>(catalog: &'a Catalog, msgid: &str) -> &'a Entry {
+        catalog.entries.iter().find(|e| e.msgid == msgid).unwrap()
+    }
+
+    fn msgids(catalog: &Catalog) -> Vec<&str> {
+        catalog.entries.iter().map(|e| e.msgid.as_str()).collect()
+    }
+
+    #[test]
+    fn one_sided_changes_are_taken() {
+        let base = catalog("msgid \"A\"\nmsgstr \"a\"\n\nmsgid \"B\"\nmsgstr \"b\"\n\nmsgid \"C\"\nmsgstr \"c\"\n");
+        let ours = catalog("msgid \"A\"\nmsgstr \"a (ours)\"\n\nmsgid \"B\"\nmsgstr \"b\"\n\nmsgid \"C\"\nmsgstr \"c\"\n");
+        let theirs = catalog("msgid \"A\"\nmsgstr \"a\"\n\nmsgid \"B\"\nmsgstr \"b (theirs)\"\n\nmsgid \"X\"\nmsgstr \"x\"\n");
+
+        let (merged, report) = merge_catalogs(&base, &ours, &theirs);
+        assert!(!report.has_conflicts());
+        assert_eq!(find(&merged, "A").msgstr(), "a (ours)");
+        assert_eq!(find(&merged, "B").msgstr(), "b (theirs)");
+        // theirs 删除了未修改的条目，新增的条目插在它在 theirs 中的前一个条目之后
+        assert_eq!(msgids(&merged), ["A", "B", "X"]);
+    }
+
+    #[test]
+    fn conflicting_translations_are_marked() {
+        let base = catalog("#: a.c:1\nmsgid \"A\"\nmsgstr \"a\"\n\nmsgid \"N\"\nmsgid_plural \"Ns\"\nmsgstr[0] \"n\"\nmsgstr[1] \"ns\"\n");
+        let ours = catalog("#: a.c:1\n#, c-format\nmsgid \"A\"\nmsgstr \"a (ours)\"\n\nmsgid \"N\"\nmsgid_plural \"Ns\"\nmsgstr[0] \"n (ours)\"\nmsgstr[1] \"ns\"\n");
+        let theirs = catalog("#: a.c:1 a.c:2\nmsgid \"A\"\nmsgstr \"a \\\"theirs\\\"\"\n\nmsgid \"N\"\nmsgid_plural \"Ns\"\nmsgstr[0] \"n (theirs)\"\nmsgstr[1] \"ns\"\n");
+
+        let (merged, report) = merge_catalogs(&base, &ours, &theirs);
+        assert_eq!(report.conflicts, vec![(None, "A".to_string()), (None, "N".to_string())]);
+
+        let a = find(&merged, "A");
+        assert_eq!(a.msgstr(), "a (ours)");
+        assert_eq!(a.flags, ["fuzzy", "c-format"]);
+        assert_eq!(a.references, ["a.c:1", "a.c:2"]);
+        assert_eq!(a.comments, ["合并冲突 ours: \"a (ours)\"", "合并冲突 theirs: \"a \\\"theirs\\\"\""]);
+
+        let n = find(&merged, "N");
+        assert!(n.is_fuzzy());
+        assert_eq!(n.comments, [
+            "合并冲突 ours msgstr[0]: \"n (ours)\"",
+            "合并冲突 ours msgstr[1]: \"ns\"",
+            "合并冲突 theirs msgstr[0]: \"n (theirs)\"",
+            "合并冲突 theirs msgstr[1]: \"ns\"",
+        ]);
+    }
+
+    #[test]
+    fn delete_against_modify_is_a_conflict() {
+        let base = catalog("msgid \"A\"\nmsgstr \"a\"\n");
+        let ours = catalog("msgid \"A\"\nmsgstr \"a (ours)\"\n");
+        let theirs = catalog("");
+
+        let (merged, report) = merge_catalogs(&base, &ours, &theirs);
+        assert_eq!(report.conflicts, vec![(None, "A".to_string())]);
+        let a = find(&merged, "A");
+        assert!(a.is_fuzzy());
+        assert_eq!(a.comments, ["合并冲突 ours: \"a (ours)\"", "合并冲突 theirs: 已删除此条目"]);
+    }
+
+    #[test]
+    fn obsolete_and_live_entries_share_a_key() {
+        let base = catalog("msgid \"A\"\nmsgstr \"a\"\n\nmsgid \"B\"\nmsgstr \"b\"\n\n#~ msgid \"C\"\n#~ msgstr \"c\"\n");
+        // ours 把 A 标记为过时，theirs 修改了 B 的译文并恢复了 C
+        let ours = catalog("#~ msgid \"A\"\n#~ msgstr \"a\"\n\nmsgid \"B\"\nmsgstr \"b\"\n\n#~ msgid \"C\"\n#~ msgstr \"c\"\n");
+        let theirs = catalog("msgid \"A\"\nmsgstr \"a\"\n\nmsgid \"B\"\nmsgstr \"b (theirs)\"\n\nmsgid \"C\"\nmsgstr \"c\"\n");
+
+        let (merged, report) = merge_catalogs(&base, &ours, &theirs);
+        assert!(!report.has_conflicts());
+        assert_eq!(msgids(&merged), ["A", "B", "C"]);
+        assert!(find(&merged, "A").obsolete);
+        assert!(!find(&merged, "C").obsolete);
+
+        // 一方标记为过时，另一方修改了译文: 合并为带新译文的过时条目
+        let theirs = catalog("msgid \"A\"\nmsgstr \"a (theirs)\"\n\nmsgid \"B\"\nmsgstr \"b\"\n\n#~ msgid \"C\"\n#~ msgstr \"c\"\n");
+        let (merged, report) = merge_catalogs(&base, &ours, &theirs);
+        assert!(!report.has_conflicts());
+        assert_eq!(msgids(&merged), ["A", "B", "C"]);
+        let a = find(&merged, "A");
+        assert!(a.obsolete);
+        assert_eq!(a.msgstr(), "a (theirs)");
+    }
+
+    #[test]
+    fn live_entry_wins_over_obsolete_duplicate() {
+        let base = catalog("msgid \"A\"\nmsgstr \"a\"\n");
+        let ours = catalog("#~ msgid \"A\"\n#~ msgstr \"old\"\n\nmsgid \"A\"\nmsgstr \"a\"\n");
+        let theirs = catalog("msgid \"A\"\nmsgstr \"a (theirs)\"\n");
+
+        let (merged, report) = merge_catalogs(&base, &ours, &theirs);
+        assert!(!report.has_conflicts());
+        assert_eq!(merged.entries.len(), 1);
+        assert_eq!(find(&merged, "A").msgstr(), "a (theirs)");
+        assert!(!find(&merged, "A").obsolete);
+    }
+}
@@ -37,7 +37,10 @@ fn restart_as_admin() -> Result<(), &'static str> {
         
     // 获取命令行参数
     let args: Vec<String> = env::args().skip(1).collect(); // 跳过可执行文件名
-    let args_str = args.join(" ");
+    let args_str = args.iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
     let args_wide: Vec<u16> = OsStr::new(&args_str)
         .encode_wide()
         .chain(std::iter::once(0))
@@ -68,7 +71,32 @@ fn restart_as_admin() -> Result<(), &'static str> {
     }
 }
 
+// 按 CommandLineToArgvW 的规则给参数加引号: 引号前和参数末尾的反斜杠要加倍，引号用反斜杠转义
+#[cfg(target_os = "windows")]
+fn quote_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        let escaped = if c == '"' { backslashes * 2 + 1 } else { backslashes };
+        quoted.extend(std::iter::repeat('\\').take(escaped));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.extend(std::iter::repeat('\\').take(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
 use crate::converters::mo_converter::MoConverter;
+use crate::converters::po_converter::CompileOptions;
+use crate::converters::po_updater::{self, UpdateOptions};
+use crate::converters::po_writer::WriteOptions;
+use crate::converters::roundtrip;
+use crate::converters::three_way;
 
 // 将字体文件嵌入到二进制文件中
 const EMBEDDED_MSYH_TTF: &[u8] = include_bytes!("../Fonts/msyh.ttf");
@@ -77,22 +105,6 @@ const EMBEDDED_MSYH_TTF: &[u8] = include_bytes!("../Fonts/msyh.ttf");
 const EMBEDDED_ICON_DATA: &[u8] = include_bytes!("../assets/icon.png");
 
 fn main() -> eframe::Result<()> {
-    // 检查是否以管理员权限运行
-    #[cfg(target_os = "windows")]
-    if !is_admin() {
-        match restart_as_admin() {
-            Ok(_) => {
-                // 重启成功，退出当前进程
-                std::process::exit(0);
-            }
-            Err(e) => {
-                // 重启失败，显示错误并继续运行
-                eprintln!("警告: {}", e);
-                eprintln!("程序将继续以普通权限运行，可能无法修改系统文件夹内容。");
-            }
-        }
-    }
-    
     // 检查命令行参数，允许直接转换
     let args: Vec<String> = env::args().collect();
     
@@ -127,6 +139,122 @@ fn main() -> eframe::Result<()> {
         }
     }
     
+    // 校验PO文件编译后再反编译是否无损
+    if args.len() >= 2 && args[1] == "--verify" {
+        if args.len() < 3 {
+            println!("用法: {} --verify input.po", args[0]);
+            process::exit(1);
+        }
+        
+        let input_path = Path::new(&args[2]);
+        println!("正在校验往返转换: {}", input_path.display());
+        
+        match roundtrip::verify_po_roundtrip(input_path, &CompileOptions::default()) {
+            Ok(report) => {
+                println!("编译了 {} 个条目，跳过 {} 个", report.compile.compiled, report.compile.skipped());
+                for difference in &report.differences {
+                    println!("{}", difference);
+                }
+                
+                if report.is_lossless() {
+                    println!("往返转换无损");
+                    process::exit(0);
+                }
+                println!("往返转换后有 {} 处不同", report.differences.len());
+                process::exit(1);
+            }
+            Err(e) => {
+                println!("校验失败: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    
+    // 三方合并PO文件，可作为git的合并驱动:
+    //   git config merge.po.driver "<程序路径> --merge3 %O %A %B"
+    //   .gitattributes 中添加: *.po merge=po
+    // 有冲突时冲突条目标记为fuzzy，返回1让git将文件标记为冲突
+    if args.len() >= 2 && args[1] == "--merge3" {
+        if args.len() < 5 {
+            println!("用法: {} --merge3 base.po ours.po theirs.po [output.po]", args[0]);
+            process::exit(2);
+        }
+        
+        let ours_path = Path::new(&args[3]);
+        let output_path = args.get(5).map(Path::new).unwrap_or(ours_path);
+        
+        match three_way::merge_po_files(Path::new(&args[2]), ours_path, Path::new(&args[4]), output_path, &WriteOptions::input_order()) {
+            Ok(report) => {
+                for (msgctxt, msgid) in &report.conflicts {
+                    match msgctxt {
+                        Some(ctx) => println!("冲突: [{}] {}", ctx, msgid),
+                        None => println!("冲突: {}", msgid),
+                    }
+                }
+                
+                if report.has_conflicts() {
+                    println!("有 {} 个条目冲突，已标记为fuzzy: {}", report.conflicts.len(), output_path.display());
+                    process::exit(1);
+                }
+                println!("合并完成: {}", output_path.display());
+                process::exit(0);
+            }
+            Err(e) => {
+                println!("合并失败: {}", e);
+                process::exit(2);
+            }
+        }
+    }
+    
+    // 按新版本的基础MO或POT文件更新语言包，类似 msgmerge --update
+    if args.len() >= 2 && args[1] == "--update" {
+        if args.len() < 4 {
+            println!("用法: {} --update mod.po base.mo|base.pot [output.po]", args[0]);
+            process::exit(1);
+        }
+        
+        let mod_path = Path::new(&args[2]);
+        let output_path = args.get(4).map(Path::new).unwrap_or(mod_path);
+        
+        match po_updater::update_po_file(mod_path, Path::new(&args[3]), output_path, &UpdateOptions::default(), &WriteOptions::input_order()) {
+            Ok(report) => {
+                for ((_, old_msgid), (_, new_msgid)) in &report.fuzzy {
+                    println!("模糊匹配: {} -> {}", old_msgid, new_msgid);
+                }
+                for (_, msgid) in &report.obsolete {
+                    println!("已过时: {}", msgid);
+                }
+                
+                println!(
+                    "更新完成: 保留 {} 个条目，{} 个模糊匹配，{} 个标记为过时: {}",
+                    report.kept, report.fuzzy.len(), report.obsolete.len(), output_path.display()
+                );
+                process::exit(0);
+            }
+            Err(e) => {
+                println!("更新失败: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    
+    // 检查是否以管理员权限运行，只有界面需要
+    // 命令行功能都在此之前处理: 以管理员权限重新启动的进程不会把结果和退出码交回调用方
+    #[cfg(target_os = "windows")]
+    if !is_admin() {
+        match restart_as_admin() {
+            Ok(_) => {
+                // 重启成功，退出当前进程
+                std::process::exit(0);
+            }
+            Err(e) => {
+                // 重启失败，显示错误并继续运行
+                eprintln!("警告: {}", e);
+                eprintln!("程序将继续以普通权限运行，可能无法修改系统文件夹内容。");
+            }
+        }
+    }
+    
     // 否则启动GUI
     let native_options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(800.0, 600.0)),
@@ -4,6 +4,8 @@ use std::io;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use crate::models::OpenAIConfig;
+use crate::converters::po_merger::{DuplicatePolicy, ObsoletePolicy};
+use crate::converters::po_writer::WriteOptions;
 
 /// 定义可选的主题
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -42,6 +44,30 @@ pub struct AppConfig {
     // 保存每个mod的启用状态 (文件名 -> 是否启用)
     pub saved_mods: HashMap<String, bool>,
     pub ignore_main_mo_entries: bool,
+    // 合并时过时条目(#~)的处理方式
+    #[serde(default)]
+    pub obsolete_policy: ObsoletePolicy,
+    // 同一语言包中重复条目的处理方式
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy,
+    // 写出PO文件时的行宽和条目顺序
+    #[serde(default)]
+    pub po_write_options: WriteOptions,
+    // 应用语言包时是否在缓存目录保留中间PO文件
+    #[serde(default)]
+    pub write_intermediate_po: bool,
+    // 编译MO文件时是否包含模糊条目
+    #[serde(default)]
+    pub compile_fuzzy: bool,
+    // 编译MO文件时是否包含未翻译的条目
+    #[serde(default)]
+    pub compile_untranslated: bool,
+    // 译文与原文相同的条目视为未翻译
+    #[serde(default)]
+    pub identical_as_untranslated: bool,
+    // 拒绝安装和应用未通过校验的语言包
+    #[serde(default)]
+    pub reject_invalid_mods: bool,
     // OpenAI API 配置
     pub openai_config: OpenAIConfig,
     // 是否启用 OpenAI 功能
@@ -61,6 +87,14 @@ impl Default for AppConfig {
             show_logs: true,
             saved_mods: HashMap::new(),
             ignore_main_mo_entries: false,
+            obsolete_policy: ObsoletePolicy::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            po_write_options: WriteOptions::default(),
+            write_intermediate_po: false,
+            compile_fuzzy: false,
+            compile_untranslated: false,
+            identical_as_untranslated: false,
+            reject_invalid_mods: false,
             openai_config: OpenAIConfig::default(),
             enable_openai: false,
         }
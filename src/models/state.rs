@@ -1,6 +1,8 @@
 use super::operation::{FileOperation, ConversionStatus, ModInfo};
 use eframe::epaint::Color32;
 use std::path::PathBuf;
+use crate::converters::po_merger::{DuplicatePolicy, ObsoletePolicy};
+use crate::converters::po_writer::WriteOptions;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ModsTab {
@@ -35,9 +37,16 @@ pub struct AppState {
     pub main_mo_file: Option<PathBuf>,
     pub mods_directory: Option<PathBuf>,
     pub output_directory: Option<PathBuf>,
-    pub cached_merged_po: Option<PathBuf>,
     pub needs_remerge: bool,
     pub ignore_main_mo_entries: bool,
+    pub obsolete_policy: ObsoletePolicy,
+    pub duplicate_policy: DuplicatePolicy,
+    pub po_write_options: WriteOptions,
+    pub write_intermediate_po: bool,
+    pub compile_fuzzy: bool,
+    pub compile_untranslated: bool,
+    pub identical_as_untranslated: bool,
+    pub reject_invalid_mods: bool,
     pub is_merging: bool,
     pub merge_progress: f32,
     pub merge_progress_anim: u32,
@@ -59,6 +68,15 @@ pub struct AppState {
     pub new_custom_model_description: String,
     pub editing_model_index: Option<usize>,
     pub merge_complete_countdown: Option<u32>,
+    // 翻译预览相关状态
+    pub show_lookup: bool,
+    pub lookup_msgctxt: String,
+    pub lookup_msgid: String,
+    pub lookup_msgid_plural: String,
+    pub lookup_count: u64,
+    // POT模板过滤条件
+    pub pot_untranslated_only: bool,
+    pub pot_pattern: String,
 }
 
 impl Default for AppState {
@@ -83,9 +101,16 @@ impl Default for AppState {
             main_mo_file: None,
             mods_directory: None,
             output_directory: None,
-            cached_merged_po: None,
             needs_remerge: false,
             ignore_main_mo_entries: false,
+            obsolete_policy: ObsoletePolicy::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            po_write_options: WriteOptions::default(),
+            write_intermediate_po: false,
+            compile_fuzzy: false,
+            compile_untranslated: false,
+            identical_as_untranslated: false,
+            reject_invalid_mods: false,
             is_merging: false,
             merge_progress: 0.0,
             merge_progress_anim: 0,
@@ -107,6 +132,15 @@ impl Default for AppState {
             new_custom_model_description: String::new(),
             editing_model_index: None,
             merge_complete_countdown: None,
+            // 翻译预览默认值
+            show_lookup: false,
+            lookup_msgctxt: String::new(),
+            lookup_msgid: String::new(),
+            lookup_msgid_plural: String::new(),
+            lookup_count: 1,
+            // POT模板默认包含所有条目
+            pot_untranslated_only: false,
+            pot_pattern: String::new(),
         }
     }
 }
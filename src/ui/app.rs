@@ -1,18 +1,29 @@
 use eframe::egui;
 use egui::{Color32, RichText, Ui};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::time::{SystemTime, UNIX_EPOCH, Duration, Instant};
 use chrono::prelude::*;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU32, Ordering};
 use rayon::ThreadPoolBuilder;
 use walkdir;
 use open;
 
 use crate::models::{AppState, ConversionType, FileOperation, AppConfig, ConversionStatus, ModStatus, ModInfo, ModsTab};
+use crate::converters::merge_cache::MergeCache;
+use crate::converters::error::ConvertError;
+use crate::converters::mo_catalog::MoCatalog;
 use crate::converters::mo_converter::MoConverter;
-use crate::converters::po_converter::PoConverter;
-use crate::converters::po_merger;
+use crate::converters::po_converter::{CompileOptions, CompileReport, PoConverter};
+use crate::converters::pipeline::{self, ApplyOptions};
+use crate::converters::progress::{CancelToken, MergeControl, MergeProgress};
+use crate::converters::po_merger::{self, DuplicateGroup, DuplicatePolicy, ObsoletePolicy};
+use crate::converters::po_parser::{self, Diagnostic, ParseMode};
+use crate::converters::po_validator;
+use crate::converters::po_writer::{self, SortOrder, WriteOptions};
+use crate::converters::pot_template::{self, TemplateFilter};
 use crate::converters::csv_converter::CsvConverter;
 
 // 添加合并状态枚举
@@ -21,6 +32,19 @@ pub enum MergeStatus {
     Progress(f32),
     Completed(PathBuf),
     Failed(String),
+    Warnings(Vec<Diagnostic>),
+    Compiled(CompileReport),
+    Validation(Vec<Diagnostic>),
+    Cancelled,
+}
+
+// 正在处理的语言包重复条目
+struct DuplicateReview {
+    name: String,
+    path: PathBuf,
+    groups: Vec<DuplicateGroup>,
+    choices: Vec<usize>, // 每组中选中保留的条目序号
+    diagnostics: Vec<Diagnostic>, // 宽松模式解析时跳过的问题
 }
 
 pub struct App {
@@ -35,6 +59,10 @@ pub struct App {
     search_text: String,
     show_install_dialog: bool,
     install_path: String,
+    lookup_catalog: Option<(PathBuf, MoCatalog)>,
+    merge_cancel: Option<CancelToken>,
+    merge_cache: Arc<Mutex<MergeCache>>,
+    duplicate_review: Option<DuplicateReview>,
 }
 
 impl Default for App {
@@ -85,6 +113,14 @@ impl App {
         state.auto_close = config.auto_close;
         state.show_logs = config.show_logs;
         state.ignore_main_mo_entries = config.ignore_main_mo_entries;
+        state.obsolete_policy = config.obsolete_policy;
+        state.duplicate_policy = config.duplicate_policy;
+        state.po_write_options = config.po_write_options;
+        state.write_intermediate_po = config.write_intermediate_po;
+        state.compile_fuzzy = config.compile_fuzzy;
+        state.compile_untranslated = config.compile_untranslated;
+        state.identical_as_untranslated = config.identical_as_untranslated;
+        state.reject_invalid_mods = config.reject_invalid_mods;
         
         // 默认显示语言包管理界面
         state.show_mods = true;
@@ -102,6 +138,10 @@ impl App {
             search_text: String::new(),
             show_install_dialog: false,
             install_path: String::new(),
+            lookup_catalog: None,
+            merge_cancel: None,
+            merge_cache: Arc::new(Mutex::new(MergeCache::new())),
+            duplicate_review: None,
         };
         
         // 启动时自动扫描语言包目录
@@ -173,6 +213,8 @@ impl App {
         
         if let Some(tx) = self.tx.clone() {
             let pool = self.thread_pool.clone();
+            let compile_options = self.compile_options();
+            let write_options = self.state.po_write_options;
             
             pool.spawn(move || {
                 let start = Instant::now();
@@ -180,16 +222,18 @@ impl App {
                 let result = match operation.conversion_type {
                     ConversionType::MoToPo => {
                         if let (Some(input), Some(output)) = (&operation.input_file, &operation.output_file) {
-                            MoConverter::convert_mo_to_po(input, output)
+                            MoConverter::convert_mo_to_po_with(input, output, &write_options)
                                 .map(|_| start.elapsed())
+                                .map_err(|e| e.to_string())
                         } else {
                             Err("输入或输出路径未设置".to_string())
                         }
                     }
                     ConversionType::PoToMo => {
                         if let (Some(input), Some(output)) = (&operation.input_file, &operation.output_file) {
-                            PoConverter::convert_po_to_mo(input, output)
+                            PoConverter::convert_po_to_mo_with(input, output, &compile_options)
                                 .map(|_| start.elapsed())
+                                .map_err(|e| e.to_string())
                         } else {
                             Err("输入或输出路径未设置".to_string())
                         }
@@ -674,9 +718,20 @@ impl App {
                             };
                             
                             ui.add(egui::ProgressBar::new(self.state.merge_progress)
+                                .desired_width(180.0)
                                 .text(RichText::new(progress_text).color(Color32::LIGHT_BLUE))
                                 .fill(Color32::LIGHT_BLUE)
                                 .animate(true));
+                            
+                            // 合并进行中可以取消，已取消后等待后台线程退出
+                            if let Some(cancel) = &self.merge_cancel {
+                                let cancelling = cancel.is_cancelled();
+                                let button = egui::Button::new(if cancelling { "正在取消..." } else { "取消" });
+                                if ui.add_enabled(!cancelling, button).clicked() {
+                                    cancel.cancel();
+                                    self.state.add_log("正在取消合并...");
+                                }
+                            }
                         } else {
                             let button_text = if self.state.needs_remerge {
                                 RichText::new("重新合并").color(Color32::LIGHT_BLUE)
@@ -688,78 +743,7 @@ impl App {
                                 .min_size(egui::vec2(130.0, 28.0));
                                 
                             if ui.add(button).clicked() {
-                                // 设置合并状态并启动线程
-                                self.state.is_merging = true;
-                                self.state.merge_progress = 0.0;
-                                self.state.merge_progress_anim = 0;
-                                
-                                // 在线程中执行合并，以避免UI冻结
-                                let tx = self.merge_tx.clone();
-                                let po_files: Vec<PathBuf> = self.state.installed_mods.iter()
-                                    .filter(|m| m.status == ModStatus::Enabled)
-                                    .map(|m| m.path.clone())
-                                    .collect();
-                                let ignore_main = self.state.ignore_main_mo_entries;
-                                
-                                self.thread_pool.spawn(move || {
-                                    // 通知开始
-                                    let _ = tx.send(MergeStatus::Started);
-                                    
-                                    // 创建缓存目录
-                                    let cache_dir = if let Some(local_dir) = dirs::data_local_dir() {
-                                        local_dir.join("BLMM").join("cache")
-                                    } else {
-                                        std::env::temp_dir().join("BLMM").join("cache")
-                                    };
-                                    
-                                    if let Err(e) = std::fs::create_dir_all(&cache_dir) {
-                                        let _ = tx.send(MergeStatus::Failed(format!("创建缓存目录失败: {}", e)));
-                                        return;
-                                    }
-                                    
-                                    // 缓存合并PO的路径
-                                    let cached_po_path = cache_dir.join("cached_merged.po");
-                                    
-                                    // 更新进度 - 添加更多的进度点
-                                    let _ = tx.send(MergeStatus::Progress(0.1)); // 10%
-                                    std::thread::sleep(std::time::Duration::from_millis(100));
-                                    
-                                    let _ = tx.send(MergeStatus::Progress(0.2)); // 20%
-                                    std::thread::sleep(std::time::Duration::from_millis(100));
-                                    
-                                    // 合并PO文件
-                                    match po_merger::merge_po_files(&po_files, &cached_po_path, ignore_main) {
-                                        Ok(_) => {
-                                            // 更新进度 - 添加更多的进度点
-                                            let _ = tx.send(MergeStatus::Progress(0.3)); // 30%
-                                            std::thread::sleep(std::time::Duration::from_millis(100));
-                                            
-                                            let _ = tx.send(MergeStatus::Progress(0.4)); // 40%
-                                            std::thread::sleep(std::time::Duration::from_millis(100));
-                                            
-                                            let _ = tx.send(MergeStatus::Progress(0.5)); // 50%
-                                            std::thread::sleep(std::time::Duration::from_millis(100));
-
-                                            let _ = tx.send(MergeStatus::Progress(0.6)); // 60%
-                                            std::thread::sleep(std::time::Duration::from_millis(100));
-
-                                            let _ = tx.send(MergeStatus::Progress(0.7)); // 70%
-                                            std::thread::sleep(std::time::Duration::from_millis(100));
-                                            
-                                            let _ = tx.send(MergeStatus::Progress(0.8)); // 80%
-                                            std::thread::sleep(std::time::Duration::from_millis(100));
-
-                                            let _ = tx.send(MergeStatus::Progress(0.9)); // 90%
-                                            
-                                            
-                                            // 完成
-                                            let _ = tx.send(MergeStatus::Completed(cached_po_path));
-                                        },
-                                        Err(e) => {
-                                            let _ = tx.send(MergeStatus::Failed(format!("合并PO文件失败: {}", e)));
-                                        }
-                                    }
-                                });
+                                self.merge_po_files();
                             }
                         }
                     });
@@ -790,6 +774,7 @@ impl App {
         let mut to_enable = None;
         let mut to_disable = None;
         let mut to_uninstall = None;
+        let mut to_review_duplicates = None;
 
         // 拖放功能已移除
         // 根据用户要求，已删除拖拽安装PO文件的功能
@@ -920,6 +905,11 @@ impl App {
                                     ui.close_menu();
                                 }
                                 
+                                if ui.button("处理重复条目").clicked() {
+                                    to_review_duplicates = Some(index);
+                                    ui.close_menu();
+                                }
+                                
                                 if ui.button("卸载").clicked() {
                                     to_uninstall = Some(index);
                                     ui.close_menu();
@@ -960,6 +950,10 @@ impl App {
         if let Some(index) = to_uninstall {
             self.uninstall_mod(index);
         }
+        
+        if let Some(index) = to_review_duplicates {
+            self.open_duplicate_review(index);
+        }
 
         // 显示日志区域（如果启用）
         if self.state.show_logs {
@@ -1003,6 +997,78 @@ impl App {
         self.render_install_dialog(ui.ctx());
     }
 
+    // 根据当前设置生成合并选项
+    fn merge_options(&self) -> po_merger::MergeOptions {
+        po_merger::MergeOptions {
+            ignore_main_entries: self.state.ignore_main_mo_entries,
+            obsolete_policy: self.state.obsolete_policy,
+            duplicate_policy: self.state.duplicate_policy,
+            write: self.state.po_write_options,
+            // 合并时跳过出错的条目，不因个别格式问题中断整个合并
+            parse_mode: ParseMode::Lenient,
+        }
+    }
+    
+    // 根据当前设置生成MO编译选项
+    fn compile_options(&self) -> CompileOptions {
+        CompileOptions {
+            include_fuzzy: self.state.compile_fuzzy,
+            include_untranslated: self.state.compile_untranslated,
+            identical_as_untranslated: self.state.identical_as_untranslated,
+            duplicate_policy: self.state.duplicate_policy,
+            ..Default::default()
+        }
+    }
+    
+    // 将语言包的校验结果写入日志，只列出前几条错误
+    fn log_validation(&mut self, diagnostics: &[Diagnostic]) {
+        const MAX_LOGGED: usize = 20;
+        
+        if diagnostics.is_empty() {
+            return;
+        }
+        
+        let errors: Vec<&Diagnostic> = diagnostics.iter().filter(|d| d.is_error()).collect();
+        let warnings = diagnostics.len() - errors.len();
+        
+        self.state.add_log(&format!("校验语言包: {} 个错误，{} 个警告", errors.len(), warnings));
+        for diagnostic in errors.iter().take(MAX_LOGGED) {
+            self.state.add_log(&format!("  {}", diagnostic));
+        }
+        if errors.len() > MAX_LOGGED {
+            self.state.add_log(&format!("  ... 其余 {} 个错误未显示", errors.len() - MAX_LOGGED));
+        }
+    }
+    
+    // 记录编译时跳过的条目数
+    fn log_compile_report(&mut self, report: &CompileReport) {
+        if report.skipped() == 0 {
+            return;
+        }
+        
+        self.state.add_log(&format!(
+            "编译MO文件时跳过了 {} 个条目: 模糊 {} 个，未翻译 {} 个，译文与原文相同 {} 个",
+            report.skipped(), report.fuzzy, report.untranslated, report.identical
+        ));
+    }
+    
+    // 将解析问题写入日志，数量过多时只显示前几条
+    fn log_diagnostics(&mut self, diagnostics: &[Diagnostic]) {
+        const MAX_LOGGED: usize = 20;
+        
+        if diagnostics.is_empty() {
+            return;
+        }
+        
+        self.state.add_log(&format!("警告: 解析时发现 {} 个问题，出错的条目已跳过", diagnostics.len()));
+        for diagnostic in diagnostics.iter().take(MAX_LOGGED) {
+            self.state.add_log(&format!("  {}", diagnostic));
+        }
+        if diagnostics.len() > MAX_LOGGED {
+            self.state.add_log(&format!("  ... 其余 {} 个问题未显示", diagnostics.len() - MAX_LOGGED));
+        }
+    }
+
     // Get or create mods cache directory
     fn get_or_create_mods_cache_dir(&self) -> Option<PathBuf> {
         // 使用 AppData\Local\BLMM\mods 目录作为默认目录
@@ -1029,40 +1095,11 @@ impl App {
         // 所有逻辑都移到了点击事件和process_merge_status方法中
     }
 
-    // Apply the cached merged PO file to the main MO file
-    fn apply_merged_po_to_mo(&mut self) {
-        // Check if we have main MO file and cached merged PO
-        if self.state.main_mo_file.is_none() {
-            self.state.add_log("错误: 请先在设置中设置主MO文件");
-            return;
-        }
-        
-        if self.state.cached_merged_po.is_none() {
-            self.state.add_log("错误: 没有可用的合并PO缓存，请先点击'合并选中PO'");
-            return;
-        }
-        
-        let main_mo_file = self.state.main_mo_file.clone().unwrap();
-        let cached_po_file = self.state.cached_merged_po.clone().unwrap();
-        
-        // 使用 AppData\Local\BLMM\cache 目录
-        let cache_dir = if let Some(local_dir) = dirs::data_local_dir() {
-            local_dir.join("BLMM").join("cache")
-        } else {
-            // 如果无法获取系统本地数据目录，使用临时目录
-            std::env::temp_dir().join("BLMM").join("cache")
-        };
-        
-        // 确保缓存目录存在
-        if let Err(e) = std::fs::create_dir_all(&cache_dir) {
-            self.state.add_log(&format!("创建缓存目录失败: {}", e));
-            return;
-        }
-        
-        // Create output MO file path - 使用用户设置的输出目录或桌面上的"BLMM导出"文件夹
-        let output_mo_path = if let Some(output_dir) = &self.state.output_directory {
+    // 确定输出MO文件路径 - 使用用户设置的输出目录或桌面上的"BLMM导出"文件夹
+    fn resolve_output_mo_path(&mut self, main_mo_file: &Path, cache_dir: &Path) -> PathBuf {
+        if let Some(output_dir) = self.state.output_directory.clone() {
             // 使用用户设置的输出目录
-            if let Err(e) = std::fs::create_dir_all(output_dir) {
+            if let Err(e) = std::fs::create_dir_all(&output_dir) {
                 self.state.add_log(&format!("创建输出目录失败: {}", e));
                 // 如果创建目录失败，回退到桌面上的"BLMM导出"文件夹
                 self.create_default_output_directory()
@@ -1084,56 +1121,6 @@ impl App {
                         cache_dir.join("blender.mo")
                     }
                 })
-        };
-        
-        // Convert the main MO file to PO first
-        let main_po_path = cache_dir.join("main.po");
-        self.state.add_log("正在将主MO文件转换为PO格式...");
-        
-        match MoConverter::convert_mo_to_po(&main_mo_file, &main_po_path) {
-            Ok(_) => {
-                self.state.add_log("主MO文件转换成功，准备与缓存PO合并...");
-                
-                // Merge main PO with cached PO
-                let all_po_files = vec![main_po_path.clone(), cached_po_file];
-                let final_merged_po = cache_dir.join("final_merged.po");
-                
-                // 记录是否使用了忽略主mo条目的选项
-                let ignore_msg = if self.state.ignore_main_mo_entries {
-                    "（已启用忽略主MO条目模式）"
-                } else {
-                    ""
-                };
-                
-                match po_merger::merge_po_files(&all_po_files, &final_merged_po, self.state.ignore_main_mo_entries) {
-                    Ok(_) => {
-                        self.state.add_log(&format!("最终PO文件合并成功{}，正在转换为MO格式...", ignore_msg));
-                        
-                        // Convert the final merged PO to MO
-                        match PoConverter::convert_po_to_mo(&final_merged_po, &output_mo_path) {
-                            Ok(_) => {
-                                // 获取输出目录用于日志显示
-                                let output_dir = output_mo_path.parent()
-                                    .map_or_else(|| "[未知目录]".to_string(), 
-                                               |dir| dir.to_string_lossy().to_string());
-                                let file_name = output_mo_path.file_name()
-                                    .map_or_else(|| "[未知文件]".to_string(), 
-                                               |name| name.to_string_lossy().to_string());
-                                self.state.add_log(&format!("合并完成! 新MO文件已保存到: {}/{}", output_dir, file_name));
-                            },
-                            Err(e) => {
-                                self.state.add_log(&format!("将合并后的PO转换为MO失败: {}", e));
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        self.state.add_log(&format!("最终PO文件合并失败: {}", e));
-                    }
-                }
-            },
-            Err(e) => {
-                self.state.add_log(&format!("将主MO文件转换为PO失败: {}", e));
-            }
         }
     }
 
@@ -1230,6 +1217,14 @@ impl App {
         let orig_auto_close = self.state.auto_close;
         let orig_show_logs = self.state.show_logs;
         let orig_ignore_main_mo_entries = self.state.ignore_main_mo_entries;
+        let orig_obsolete_policy = self.state.obsolete_policy;
+        let orig_duplicate_policy = self.state.duplicate_policy;
+        let orig_po_write_options = self.state.po_write_options;
+        let orig_write_intermediate_po = self.state.write_intermediate_po;
+        let orig_compile_fuzzy = self.state.compile_fuzzy;
+        let orig_compile_untranslated = self.state.compile_untranslated;
+        let orig_identical_as_untranslated = self.state.identical_as_untranslated;
+        let orig_reject_invalid_mods = self.state.reject_invalid_mods;
         let orig_theme = self.config.theme.clone();
         
         // 添加主题设置部分
@@ -1289,13 +1284,15 @@ impl App {
                     self.state.main_mo_file = Some(mo_path.clone());
                     self.config.main_mo_file = Some(mo_path.clone());
                     
-                    // 清除合并缓存，因为主MO文件已更改
-                    self.state.cached_merged_po = None;
+                    // 主MO文件已更改，需要重新合并
                     self.state.needs_remerge = true;
                     
                     // 添加日志
                     self.state.add_log(&format!("已设置主MO文件: {}", mo_path.display()));
                     
+                    // 检查MO文件的哈希表
+                    self.inspect_base_mo(&mo_path);
+                    
                     // 将文件克隆到BLMM文件夹
                     self.clone_main_mo_to_blmm(&mo_path);
                 }
@@ -1309,8 +1306,7 @@ impl App {
                 self.state.main_mo_file = None;
                 self.config.main_mo_file = None;
                 
-                // 清除合并缓存，因为主MO文件已更改
-                self.state.cached_merged_po = None;
+                // 主MO文件已更改，需要重新合并
                 self.state.needs_remerge = true;
                 
                 self.state.add_log("已清除主MO文件设置");
@@ -1325,6 +1321,18 @@ impl App {
             ui.label("(将当前的基础MO文件导出为独立文件，不做任何合并)");
         });
         
+        // 从基础MO文件生成POT模板，新语言包可以从模板开始翻译
+        ui.horizontal(|ui| {
+            if ui.button("生成POT模板").clicked() {
+                self.export_pot_template();
+            }
+            ui.checkbox(&mut self.state.pot_untranslated_only, "仅译文与原文相同的条目")
+                .on_hover_text("MO文件中不含未翻译的条目，勾选后只包含基础MO文件中译文与原文相同(通常是沿用了原文)的条目");
+            ui.label("匹配:");
+            ui.text_edit_singleline(&mut self.state.pot_pattern)
+                .on_hover_text("只包含原文或上下文匹配的条目，不区分大小写，支持 * 和 ? 通配符，留空则不过滤");
+        });
+        
         ui.add_space(4.0);
         
         ui.horizontal(|ui| {
@@ -1390,6 +1398,70 @@ impl App {
             ui.checkbox(&mut self.state.ignore_main_mo_entries, "忽略主mo合并")
                 .on_hover_text("启用后，语言包中与主MO文件重复的条目将被忽略，保留主MO文件中的原始翻译");
             
+            // 过时条目(#~)的处理方式
+            ui.horizontal(|ui| {
+                ui.label("过时条目(#~):")
+                    .on_hover_text("Poedit或msgmerge导出的PO文件中以#~开头的过时条目");
+                egui::ComboBox::from_id_source("obsolete_policy")
+                    .selected_text(match self.state.obsolete_policy {
+                        ObsoletePolicy::Drop => "丢弃",
+                        ObsoletePolicy::Keep => "保留",
+                        ObsoletePolicy::Resurrect => "恢复为正常条目",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.state.obsolete_policy, ObsoletePolicy::Drop, "丢弃");
+                        ui.selectable_value(&mut self.state.obsolete_policy, ObsoletePolicy::Keep, "保留");
+                        ui.selectable_value(&mut self.state.obsolete_policy, ObsoletePolicy::Resurrect, "恢复为正常条目");
+                    });
+            });
+            
+            // 同一语言包中重复条目的处理方式
+            ui.horizontal(|ui| {
+                ui.label("重复条目:")
+                    .on_hover_text("同一个语言包中 msgctxt 和 msgid 都相同的条目，可以在语言包的右键菜单中逐条选择");
+                egui::ComboBox::from_id_source("duplicate_policy")
+                    .selected_text(duplicate_policy_name(self.state.duplicate_policy))
+                    .show_ui(ui, |ui| {
+                        for policy in [DuplicatePolicy::LastWins, DuplicatePolicy::FirstWins, DuplicatePolicy::Error] {
+                            ui.selectable_value(&mut self.state.duplicate_policy, policy, duplicate_policy_name(policy));
+                        }
+                    });
+            });
+            
+            // 写出PO文件的格式，与msgcat一致
+            ui.checkbox(&mut self.state.po_write_options.stable, "稳定输出(适合git)")
+                .on_hover_text("固定按msgid排序并按默认行宽折行，内容没有变化时不更新时间戳也不改写文件，重新生成不会产生差异");
+            ui.add_enabled_ui(!self.state.po_write_options.stable, |ui| ui.horizontal(|ui| {
+                ui.label("PO条目顺序:");
+                egui::ComboBox::from_id_source("po_sort_order")
+                    .selected_text(sort_order_name(self.state.po_write_options.sort))
+                    .show_ui(ui, |ui| {
+                        for order in [SortOrder::Key, SortOrder::Reference, SortOrder::Input] {
+                            ui.selectable_value(&mut self.state.po_write_options.sort, order, sort_order_name(order));
+                        }
+                    });
+                
+                ui.label("行宽:");
+                ui.add(egui::DragValue::new(&mut self.state.po_write_options.width).clamp_range(0..=200))
+                    .on_hover_text(format!("超过行宽的字符串在空格和中文处折行，0 表示只在换行符处拆分，msgcat 默认为 {}", po_writer::DEFAULT_WIDTH));
+            }));
+            
+            ui.checkbox(&mut self.state.write_intermediate_po, "保留中间PO文件")
+                .on_hover_text("应用语言包时在缓存目录写出 main.po 和 final_merged.po，便于排查合并结果");
+            
+            // MO编译选项，默认与msgfmt一致
+            ui.checkbox(&mut self.state.compile_fuzzy, "编译模糊(fuzzy)条目")
+                .on_hover_text("默认跳过标记为fuzzy的条目，这些翻译通常还需要校对");
+            ui.checkbox(&mut self.state.compile_untranslated, "编译未翻译的条目")
+                .on_hover_text("默认跳过译文为空的条目，避免Blender界面中的文字显示为空白");
+            ui.add_enabled(
+                !self.state.compile_untranslated,
+                egui::Checkbox::new(&mut self.state.identical_as_untranslated, "译文与原文相同视为未翻译"),
+            ).on_hover_text("跳过译文与原文完全相同的条目");
+            
+            ui.checkbox(&mut self.state.reject_invalid_mods, "拒绝未通过校验的语言包")
+                .on_hover_text("安装和应用语言包时按 msgfmt --check 的规则校验，有错误的语言包不会被安装，也不会生成新的MO文件");
+            
             ui.horizontal(|ui| {
                 ui.label(format!("线程池: {} 线程", num_cpus::get()));
             });
@@ -1402,6 +1474,14 @@ impl App {
            orig_auto_close != self.state.auto_close ||
            orig_show_logs != self.state.show_logs ||
            orig_ignore_main_mo_entries != self.state.ignore_main_mo_entries ||
+           orig_obsolete_policy != self.state.obsolete_policy ||
+           orig_duplicate_policy != self.state.duplicate_policy ||
+           orig_po_write_options != self.state.po_write_options ||
+           orig_write_intermediate_po != self.state.write_intermediate_po ||
+           orig_compile_fuzzy != self.state.compile_fuzzy ||
+           orig_compile_untranslated != self.state.compile_untranslated ||
+           orig_identical_as_untranslated != self.state.identical_as_untranslated ||
+           orig_reject_invalid_mods != self.state.reject_invalid_mods ||
            orig_theme != self.config.theme {
             // 保存设置到配置文件
             self.config.main_mo_file = self.state.main_mo_file.clone();
@@ -1410,6 +1490,14 @@ impl App {
             self.config.auto_close = self.state.auto_close;
             self.config.show_logs = self.state.show_logs;
             self.config.ignore_main_mo_entries = self.state.ignore_main_mo_entries;
+            self.config.obsolete_policy = self.state.obsolete_policy;
+            self.config.duplicate_policy = self.state.duplicate_policy;
+            self.config.po_write_options = self.state.po_write_options;
+            self.config.write_intermediate_po = self.state.write_intermediate_po;
+            self.config.compile_fuzzy = self.state.compile_fuzzy;
+            self.config.compile_untranslated = self.state.compile_untranslated;
+            self.config.identical_as_untranslated = self.state.identical_as_untranslated;
+            self.config.reject_invalid_mods = self.state.reject_invalid_mods;
             
             if let Err(e) = self.config.save() {
                 self.state.add_log(&format!("无法保存配置: {}", e));
@@ -1475,7 +1563,8 @@ impl App {
                         .as_secs()));
                     
                     // 转换CSV到PO
-                    match CsvConverter::convert_csv_to_po(&file, &temp_po_path) {
+                    let options = WriteOptions { sort: SortOrder::Input, ..self.state.po_write_options };
+                    match CsvConverter::convert_csv_to_po_with(&file, &temp_po_path, &options) {
                         Ok(_) => {
                             self.state.add_log(&format!("成功将CSV转换为PO: {}", temp_po_path.display()));
                             temp_po_path
@@ -1489,6 +1578,22 @@ impl App {
                     file.clone()
                 };
                 
+                // 按 msgfmt --check 的规则校验，设置中要求时拒绝安装有错误的语言包
+                let valid = match po_validator::validate_file(&processed_file) {
+                    Ok(diagnostics) => {
+                        self.log_validation(&diagnostics);
+                        !po_validator::has_errors(&diagnostics)
+                    },
+                    Err(e) => {
+                        self.state.add_log(&format!("无法校验 {}: {}", file.display(), e));
+                        false
+                    }
+                };
+                if !valid && self.state.reject_invalid_mods {
+                    self.state.add_log(&format!("语言包未通过校验，已拒绝安装: {}", file.display()));
+                    continue;
+                }
+                
                 // 创建新的MOD信息
                 let orig_file_name = file.file_name().unwrap_or_default().to_string_lossy().to_string();
                 let mut file_name = processed_file.file_name().unwrap_or_default().to_string_lossy().to_string();
@@ -1782,6 +1887,14 @@ impl App {
         self.config.auto_close = self.state.auto_close;
         self.config.show_logs = self.state.show_logs;
         self.config.ignore_main_mo_entries = self.state.ignore_main_mo_entries;
+        self.config.obsolete_policy = self.state.obsolete_policy;
+        self.config.duplicate_policy = self.state.duplicate_policy;
+        self.config.po_write_options = self.state.po_write_options;
+        self.config.write_intermediate_po = self.state.write_intermediate_po;
+        self.config.compile_fuzzy = self.state.compile_fuzzy;
+        self.config.compile_untranslated = self.state.compile_untranslated;
+        self.config.identical_as_untranslated = self.state.identical_as_untranslated;
+        self.config.reject_invalid_mods = self.state.reject_invalid_mods;
         
         // 保存配置
         if let Err(e) = self.config.save() {
@@ -1826,6 +1939,15 @@ impl App {
         }
     }
 
+    // 检查基础MO文件的哈希表是否与字符串表一致，结果写入日志
+    fn inspect_base_mo(&mut self, mo_path: &Path) {
+        match MoConverter::verify_hash_table(mo_path) {
+            Ok(true) => self.state.add_log("基础MO文件哈希表检查通过"),
+            Ok(false) => self.state.add_log("基础MO文件不含哈希表，运行时将使用二分查找"),
+            Err(e) => self.state.add_log(&format!("警告: 基础MO文件检查未通过: {}", e)),
+        }
+    }
+
     // 处理合并状态更新
     fn process_merge_status(&mut self) {
         // 更新动画计数器
@@ -1845,7 +1967,8 @@ impl App {
             }
         }
         
-        if let Ok(status) = self.merge_rx.try_recv() {
+        // 一帧内处理所有积压的状态更新，避免进度落后于实际
+        while let Ok(status) = self.merge_rx.try_recv() {
             match status {
                 MergeStatus::Started => {
                     self.state.is_merging = true;
@@ -1855,16 +1978,12 @@ impl App {
                 },
                 MergeStatus::Progress(progress) => {
                     // 设置目标进度，而不是直接设置当前进度
+                    // 进度更新很频繁，只显示在进度条上，不写入日志
                     self.state.target_merge_progress = Some(progress);
-                    
-                    // 从进度更新日志，确保显示百分比
-                    let percent = (progress * 100.0) as i32;
-                    self.state.add_log(&format!("合并进度: {}%", percent));
-                    
-                    // 移除中间停顿的逻辑，让进度条直接平滑过渡到目标值
-                    // 不再需要特殊处理99%的情况
                 },
                 MergeStatus::Completed(path) => {
+                    self.merge_cancel = None;
+                    
                     // 先设置进度为100%，再设置合并状态为false
                     self.state.merge_progress = 1.0;
                     self.state.target_merge_progress = Some(1.0);
@@ -1881,19 +2000,17 @@ impl App {
                     // 检查是否为 OpenAI 响应（使用 PathBuf 传递文本响应）
                     // 检查是否为 OpenAI 响应（使用 PathBuf 传递文本响应）
                     if path.is_absolute() {
-                        // 正常的文件路径，表示合并完成
-                        self.state.cached_merged_po = Some(path.clone());
+                        // 正常的文件路径，表示新MO文件已生成
                         self.state.needs_remerge = false;
-                        self.state.add_log(&format!("PO文件合并成功，已生成缓存文件: {}", path.display()));
-                        self.state.add_log("点击'应用到MO文件'将合并结果应用到主MO文件");
                         
-                        // 如果存在已设置的主MO文件，自动应用
-                        if self.state.main_mo_file.is_some() {
-                            self.state.add_log("自动应用到主MO文件...");
-                            if self.state.cached_merged_po.is_some() {
-                                self.apply_merged_po_to_mo();
-                            }
-                        }
+                        // 获取输出目录用于日志显示
+                        let output_dir = path.parent()
+                            .map_or_else(|| "[未知目录]".to_string(), 
+                                       |dir| dir.to_string_lossy().to_string());
+                        let file_name = path.file_name()
+                            .map_or_else(|| "[未知文件]".to_string(), 
+                                       |name| name.to_string_lossy().to_string());
+                        self.state.add_log(&format!("合并完成! 新MO文件已保存到: {}/{}", output_dir, file_name));
                     } else {
                         // 非绝对路径，表示 OpenAI 响应文本
                         let response_text = path.to_string_lossy().to_string();
@@ -1902,6 +2019,22 @@ impl App {
                         self.state.add_log("收到 OpenAI API 响应");
                     }
                 },
+                MergeStatus::Warnings(diagnostics) => {
+                    self.log_diagnostics(&diagnostics);
+                },
+                MergeStatus::Compiled(report) => {
+                    self.log_compile_report(&report);
+                },
+                MergeStatus::Validation(diagnostics) => {
+                    self.log_validation(&diagnostics);
+                },
+                MergeStatus::Cancelled => {
+                    self.merge_cancel = None;
+                    self.state.is_merging = false;
+                    self.state.merge_progress = 0.0;
+                    self.state.target_merge_progress = None;
+                    self.state.add_log("合并已取消，未生成输出文件");
+                },
                 MergeStatus::Failed(error) => {
                     // 检查是否为 OpenAI 错误
                     if self.state.openai_is_processing {
@@ -1909,6 +2042,7 @@ impl App {
                         self.state.openai_last_error = Some(error.clone());
                         self.state.add_log(&format!("OpenAI 请求失败: {}", error));
                     } else {
+                        self.merge_cancel = None;
                         self.state.is_merging = false;
                         self.state.add_log(&format!("合并失败: {}", error));
                     }
@@ -1975,6 +2109,266 @@ impl App {
         }
     }
 
+    // 翻译预览窗口: 查询MO文件中某个原文在Blender中实际显示的译文
+    fn render_lookup_window(&mut self, ctx: &egui::Context) {
+        if !self.state.show_lookup {
+            return;
+        }
+        
+        let mut open = true;
+        let mut load_path = None;
+        
+        egui::Window::new("翻译预览")
+            .open(&mut open)
+            .collapsible(false)
+            .min_width(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("MO文件:");
+                    match &self.lookup_catalog {
+                        Some((path, catalog)) => {
+                            let file_name = path.file_name()
+                                .map(|f| f.to_string_lossy().to_string())
+                                .unwrap_or_else(|| path.display().to_string());
+                            ui.label(format!("{} ({} 条)", file_name, catalog.len()))
+                                .on_hover_text(path.to_string_lossy().to_string());
+                        }
+                        None => {
+                            ui.label("[未加载]");
+                        }
+                    }
+                });
+                
+                ui.horizontal(|ui| {
+                    if ui.button("加载主MO文件").clicked() {
+                        match &self.state.main_mo_file {
+                            Some(path) => load_path = Some(path.clone()),
+                            None => self.state.add_log("错误: 请先在设置中设置主MO文件"),
+                        }
+                    }
+                    
+                    if ui.button("选择MO文件").clicked() {
+                        load_path = rfd::FileDialog::new()
+                            .add_filter("MO文件", &["mo"])
+                            .set_title("选择要预览的MO文件")
+                            .pick_file();
+                    }
+                });
+                
+                ui.separator();
+                
+                egui::Grid::new("lookup_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("上下文:");
+                    ui.text_edit_singleline(&mut self.state.lookup_msgctxt);
+                    ui.end_row();
+                    
+                    ui.label("原文:");
+                    ui.text_edit_singleline(&mut self.state.lookup_msgid);
+                    ui.end_row();
+                    
+                    ui.label("复数原文:");
+                    ui.text_edit_singleline(&mut self.state.lookup_msgid_plural);
+                    ui.end_row();
+                    
+                    if !self.state.lookup_msgid_plural.is_empty() {
+                        ui.label("数量:");
+                        ui.add(egui::DragValue::new(&mut self.state.lookup_count));
+                        ui.end_row();
+                    }
+                });
+                
+                ui.separator();
+                
+                if let Some((_, catalog)) = &self.lookup_catalog {
+                    let msgctxt = self.state.lookup_msgctxt.as_str();
+                    let msgid = self.state.lookup_msgid.as_str();
+                    let msgid_plural = self.state.lookup_msgid_plural.as_str();
+                    let n = self.state.lookup_count;
+                    
+                    let translated = match (msgctxt.is_empty(), msgid_plural.is_empty()) {
+                        (true, true) => catalog.gettext(msgid),
+                        (false, true) => catalog.pgettext(msgctxt, msgid),
+                        (true, false) => catalog.ngettext(msgid, msgid_plural, n),
+                        (false, false) => catalog.npgettext(msgctxt, msgid, msgid_plural, n),
+                    };
+                    
+                    ui.horizontal(|ui| {
+                        ui.label("显示结果:");
+                        ui.label(RichText::new(translated).strong());
+                    });
+                    
+                    if !msgid_plural.is_empty() {
+                        let forms = catalog.plural_forms();
+                        ui.label(format!("复数形式: 第 {} 种 (共 {} 种)", forms.index(n), forms.nplurals));
+                    }
+                } else {
+                    ui.label("请先加载MO文件");
+                }
+            });
+        
+        if let Some(path) = load_path {
+            match MoCatalog::open(&path) {
+                Ok(catalog) => {
+                    self.state.add_log(&format!("已加载预览MO文件: {} ({} 条)", path.display(), catalog.len()));
+                    self.lookup_catalog = Some((path, catalog));
+                }
+                Err(e) => self.state.add_log(&format!("加载预览MO文件失败: {}", e)),
+            }
+        }
+        
+        if !open {
+            self.state.show_lookup = false;
+        }
+    }
+
+    // 查找语言包中的重复条目，有重复时打开选择窗口
+    fn open_duplicate_review(&mut self, index: usize) {
+        let Some(mod_info) = self.state.installed_mods.get(index) else {
+            return;
+        };
+        let name = mod_info.name.clone();
+        let path = mod_info.path.clone();
+        
+        // 与合并时一样使用宽松模式，有可恢复的语法问题时也能检查重复条目
+        let (catalog, diagnostics) = match po_parser::parse_file(&path, ParseMode::Lenient) {
+            Ok(result) => (result.catalog, result.diagnostics),
+            Err(e) => {
+                self.state.add_log(&format!("无法解析语言包 {}: {}", name, e));
+                return;
+            }
+        };
+        self.log_diagnostics(&diagnostics);
+        
+        let groups = po_merger::find_duplicates(&catalog.entries);
+        if groups.is_empty() {
+            self.state.add_log(&format!("语言包 {} 中没有重复的条目", name));
+            return;
+        }
+        
+        // 默认选中按当前设置会保留的条目
+        let choices = groups.iter()
+            .map(|group| match self.state.duplicate_policy {
+                DuplicatePolicy::FirstWins => 0,
+                _ => group.entries.len() - 1,
+            })
+            .collect();
+        self.state.add_log(&format!("语言包 {} 中有 {} 组重复的条目", name, groups.len()));
+        self.duplicate_review = Some(DuplicateReview { name, path, groups, choices, diagnostics });
+    }
+    
+    // 重复条目选择窗口
+    fn render_duplicate_window(&mut self, ctx: &egui::Context) {
+        let Some(review) = &mut self.duplicate_review else {
+            return;
+        };
+        
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        
+        egui::Window::new(format!("重复条目 - {}", review.name))
+            .open(&mut open)
+            .collapsible(false)
+            .min_width(500.0)
+            .show(ctx, |ui| {
+                ui.label(format!("共 {} 组重复的条目，每组选择要保留的一条，保存后其余的条目将从语言包中删除", review.groups.len()));
+                
+                if !review.diagnostics.is_empty() {
+                    ui.colored_label(Color32::YELLOW, format!("解析时发现 {} 个问题，出错的条目已跳过，保存时不会写回这些条目", review.diagnostics.len()));
+                    egui::CollapsingHeader::new("解析问题")
+                        .id_source("duplicate_diagnostics")
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                                for diagnostic in &review.diagnostics {
+                                    ui.label(diagnostic.to_string());
+                                }
+                            });
+                        });
+                }
+                ui.separator();
+                
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for (i, (group, choice)) in review.groups.iter().zip(review.choices.iter_mut()).enumerate() {
+                        ui.push_id(i, |ui| {
+                            let title = match &group.key {
+                                (Some(msgctxt), msgid) => format!("[{}] {}", msgctxt, msgid),
+                                (None, msgid) => msgid.clone(),
+                            };
+                            ui.label(RichText::new(title).strong());
+                            
+                            for (j, entry) in group.entries.iter().enumerate() {
+                                let mut text = format!("第 {} 行: {}", entry.line_number, entry.msgstr());
+                                if entry.is_fuzzy() {
+                                    text.push_str(" (fuzzy)");
+                                }
+                                ui.radio_value(choice, j, text);
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+                
+                ui.horizontal(|ui| {
+                    if ui.button("保存选择").clicked() {
+                        save = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        
+        if save {
+            if let Some(review) = self.duplicate_review.take() {
+                self.save_duplicate_choices(review);
+            }
+        } else if cancel || !open {
+            self.duplicate_review = None;
+        }
+    }
+    
+    // 每组重复条目只保留选中的一条，写回语言包文件
+    fn save_duplicate_choices(&mut self, review: DuplicateReview) {
+        let result = po_parser::parse_file(&review.path, ParseMode::Lenient).and_then(|result| {
+            let mut catalog = result.catalog;
+            // 文件在选择期间被修改时位置可能已经改变，此时不写入
+            let current = po_merger::find_duplicates(&catalog.entries);
+            let unchanged = current.len() == review.groups.len()
+                && current.iter().zip(&review.groups).all(|(a, b)| a.positions == b.positions);
+            if !unchanged {
+                return Ok(false);
+            }
+            
+            let dropped: HashSet<usize> = review.groups.iter()
+                .zip(&review.choices)
+                .flat_map(|(group, &choice)| {
+                    group.positions.iter()
+                        .enumerate()
+                        .filter(move |(j, _)| *j != choice)
+                        .map(|(_, &position)| position)
+                })
+                .collect();
+            catalog.entries = catalog.entries.into_iter()
+                .enumerate()
+                .filter(|(i, _)| !dropped.contains(i))
+                .map(|(_, entry)| entry)
+                .collect();
+            // 保持语言包原来的条目顺序
+            let options = WriteOptions { sort: SortOrder::Input, ..self.state.po_write_options };
+            po_writer::write_po_file(&catalog, &review.path, &options)?;
+            Ok(true)
+        });
+        
+        match result {
+            Ok(true) => {
+                self.state.add_log(&format!("已处理语言包 {} 中的 {} 组重复条目", review.name, review.groups.len()));
+                self.state.needs_remerge = true;
+            }
+            Ok(false) => self.state.add_log(&format!("语言包 {} 已被修改，请重新检查重复条目", review.name)),
+            Err(e) => self.state.add_log(&format!("保存语言包 {} 失败: {}", review.name, e)),
+        }
+    }
+
     // 重命名对话框
     fn render_rename_dialog(&mut self, ctx: &egui::Context) {
         if self.state.rename_mod_index.is_some() {
@@ -2220,6 +2614,7 @@ impl App {
                         let file_path = found_mo_files[0].clone();
                         let orig_path = file_path.clone(); // 克隆一份用于日志显示
                         // 复制到BLMM目录并使用BLMM目录中的文件
+                        self.inspect_base_mo(&file_path);
                         if let Some(blmm_path) = self.clone_main_mo_to_blmm(&file_path) {
                             self.state.main_mo_file = Some(blmm_path.clone());
                             self.config.main_mo_file = Some(blmm_path);
@@ -2241,6 +2636,7 @@ impl App {
                             
                             let orig_path = selected_path.clone(); // 克隆一份用于日志显示
                             // 复制到BLMM目录并使用BLMM目录中的文件
+                            self.inspect_base_mo(&selected_path);
                             if let Some(blmm_path) = self.clone_main_mo_to_blmm(&selected_path) {
                                 self.state.main_mo_file = Some(blmm_path.clone());
                                 self.config.main_mo_file = Some(blmm_path);
@@ -2263,6 +2659,7 @@ impl App {
                         .pick_file() {
                             // 复制到BLMM目录并使用BLMM目录中的文件
                             let orig_file = file.clone(); // 克隆一份用于日志显示
+                            self.inspect_base_mo(&file);
                             if let Some(blmm_path) = self.clone_main_mo_to_blmm(&file) {
                                 self.state.main_mo_file = Some(blmm_path.clone());
                                 self.config.main_mo_file = Some(blmm_path);
@@ -2321,6 +2718,30 @@ impl App {
     }
 
     // 导出基础文件（不合并）
+    fn export_pot_template(&mut self) {
+        let Some(base_mo_file) = self.state.main_mo_file.clone() else {
+            self.state.add_log("错误: 请先在设置中设置主MO文件");
+            return;
+        };
+        
+        let Some(output_path) = rfd::FileDialog::new()
+            .add_filter("POT模板", &["pot"])
+            .set_title("保存POT模板")
+            .set_file_name("blender.pot")
+            .save_file() else {
+            return;
+        };
+        
+        let filter = TemplateFilter {
+            untranslated_only: self.state.pot_untranslated_only,
+            pattern: self.state.pot_pattern.clone(),
+        };
+        match pot_template::write_pot_file(&base_mo_file, &output_path, &filter, &self.state.po_write_options) {
+            Ok(count) => self.state.add_log(&format!("已生成POT模板 ({} 个条目): {}", count, output_path.display())),
+            Err(e) => self.state.add_log(&format!("生成POT模板失败: {}", e)),
+        }
+    }
+    
     fn export_base_mo_file(&mut self) {
         // 检查是否有主MO文件
         if self.state.main_mo_file.is_none() {
@@ -2385,10 +2806,10 @@ impl App {
     // 添加合并PO文件方法
     fn merge_po_files(&mut self) {
         // 检查是否有主MO文件
-        if self.state.main_mo_file.is_none() {
+        let Some(main_mo_file) = self.state.main_mo_file.clone() else {
             self.state.add_log("错误: 请先在设置中设置主MO文件");
             return;
-        }
+        };
         
         // 检查是否有启用的语言包
         let enabled_mods = self.state.installed_mods.iter()
@@ -2400,6 +2821,27 @@ impl App {
             return;
         }
         
+        // 使用 AppData\Local\BLMM\cache 目录
+        let cache_dir = if let Some(local_dir) = dirs::data_local_dir() {
+            local_dir.join("BLMM").join("cache")
+        } else {
+            // 如果无法获取系统本地数据目录，使用临时目录
+            std::env::temp_dir().join("BLMM").join("cache")
+        };
+        
+        // 确保缓存目录存在
+        if let Err(e) = std::fs::create_dir_all(&cache_dir) {
+            self.state.add_log(&format!("创建缓存目录失败: {}", e));
+            return;
+        }
+        
+        let output_mo_path = self.resolve_output_mo_path(&main_mo_file, &cache_dir);
+        
+        // 记录是否使用了忽略主mo条目的选项
+        if self.state.ignore_main_mo_entries {
+            self.state.add_log("已启用忽略主MO条目模式");
+        }
+        
         // 设置合并状态
         self.state.is_merging = true;
         self.state.merge_progress = 0.0;
@@ -2411,54 +2853,75 @@ impl App {
             .filter(|m| m.status == ModStatus::Enabled)
             .map(|m| m.path.clone())
             .collect();
-        let ignore_main = self.state.ignore_main_mo_entries;
+        let apply_options = ApplyOptions {
+            merge: self.merge_options(),
+            compile: self.compile_options(),
+            // 中间PO文件只在设置中要求时写出
+            intermediate_dir: self.state.write_intermediate_po.then(|| cache_dir.clone()),
+            reject_invalid: self.state.reject_invalid_mods,
+        };
+        
+        // 输出MO文件的大小按主MO文件估算，用于显示写入进度
+        let expected_size = std::fs::metadata(&main_mo_file).map(|m| m.len()).unwrap_or(0);
+        
+        let cancel = CancelToken::new();
+        self.merge_cancel = Some(cancel.clone());
+        
+        // 解析占进度的40%，处理冲突占40%，写入占20%；百分比变化时才发送，避免消息过多
+        let progress_tx = tx.clone();
+        let last_percent = AtomicU32::new(0);
+        let control = MergeControl::new()
+            .with_cancel(cancel)
+            .with_progress(move |progress| {
+                let fraction = match progress {
+                    MergeProgress::FilesParsed { done, total } => 0.4 * done as f32 / total.max(1) as f32,
+                    MergeProgress::EntriesResolved { done, total } => 0.4 + 0.4 * done as f32 / total.max(1) as f32,
+                    MergeProgress::BytesWritten(written) => 0.8 + 0.2 * (written as f32 / expected_size.max(1) as f32).min(1.0),
+                };
+                let percent = (fraction * 100.0) as u32;
+                if last_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+                    let _ = progress_tx.send(MergeStatus::Progress(fraction));
+                }
+            });
+        
+        let merge_cache = Arc::clone(&self.merge_cache);
         
         self.thread_pool.spawn(move || {
             // 通知开始
             let _ = tx.send(MergeStatus::Started);
             
-            // 创建缓存目录
-            let cache_dir = if let Some(local_dir) = dirs::data_local_dir() {
-                local_dir.join("BLMM").join("cache")
-            } else {
-                std::env::temp_dir().join("BLMM").join("cache")
-            };
-            
-            if let Err(e) = std::fs::create_dir_all(&cache_dir) {
-                let _ = tx.send(MergeStatus::Failed(format!("创建缓存目录失败: {}", e)));
-                return;
-            }
-            
-            // 缓存合并PO的路径
-            let cached_po_path = cache_dir.join("cached_merged.po");
-            
-            // 更新进度 - 添加更多的进度点
-            let _ = tx.send(MergeStatus::Progress(0.1)); // 10%
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            
-            let _ = tx.send(MergeStatus::Progress(0.2)); // 20%
-            std::thread::sleep(std::time::Duration::from_millis(100));
-            
-            let _ = tx.send(MergeStatus::Progress(0.3)); // 30%
-            
-            // 合并PO文件
-            match po_merger::merge_po_files(&po_files, &cached_po_path, ignore_main) {
-                Ok(_) => {
-                    // 更新进度 - 添加更多的进度点
-                    let _ = tx.send(MergeStatus::Progress(0.5)); // 50%
-                    std::thread::sleep(std::time::Duration::from_millis(100));
-                    
-                    let _ = tx.send(MergeStatus::Progress(0.9)); // 70%
-                    std::thread::sleep(std::time::Duration::from_millis(100));
-                    
-                    let _ = tx.send(MergeStatus::Progress(1.0)); // 100%
-                    std::thread::sleep(std::time::Duration::from_millis(100));
+            // 在内存中合并主MO文件与语言包，直接生成新的MO文件；未改变的文件沿用缓存的解析结果
+            // 上一次合并中途panic时锁会被标记为中毒；上次的合并结果在合并开始时取出、成功后才放回，
+            // 缓存不会处于不一致的状态，可以继续使用
+            let mut cache = merge_cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match pipeline::apply_mods_to_mo(&mut cache, &main_mo_file, &po_files, &output_mo_path, &apply_options, &control) {
+                Ok(report) => {
+                    if !report.diagnostics.is_empty() {
+                        let _ = tx.send(MergeStatus::Warnings(report.diagnostics));
+                    }
+                    if !report.validation.is_empty() {
+                        let _ = tx.send(MergeStatus::Validation(report.validation));
+                    }
+                    let _ = tx.send(MergeStatus::Compiled(report.compile));
                     
                     // 完成
-                    let _ = tx.send(MergeStatus::Completed(cached_po_path));
+                    let _ = tx.send(MergeStatus::Completed(output_mo_path));
+                },
+                Err(ConvertError::Cancelled) => {
+                    let _ = tx.send(MergeStatus::Cancelled);
+                },
+                Err(ConvertError::Rejected(errors)) => {
+                    let count = errors.len();
+                    let _ = tx.send(MergeStatus::Validation(errors));
+                    let _ = tx.send(MergeStatus::Failed(format!("语言包有 {} 个错误未通过校验，未生成新的MO文件", count)));
+                },
+                Err(ConvertError::Duplicates(duplicates)) => {
+                    let count = duplicates.len();
+                    let _ = tx.send(MergeStatus::Validation(duplicates));
+                    let _ = tx.send(MergeStatus::Failed(format!("语言包中有 {} 个重复的条目，未生成新的MO文件，可在语言包的右键菜单中选择保留哪一条", count)));
                 },
                 Err(e) => {
-                    let _ = tx.send(MergeStatus::Failed(format!("合并PO文件失败: {}", e)));
+                    let _ = tx.send(MergeStatus::Failed(e.to_string()));
                 }
             }
         });
@@ -2919,6 +3382,11 @@ impl eframe::App for App {
                         ui.close_menu();
                     }
                     
+                    if ui.button("翻译预览").clicked() {
+                        self.state.show_lookup = true;
+                        ui.close_menu();
+                    }
+                    
                     ui.separator();
                     
                     if ui.button("批量处理").clicked() {
@@ -2971,7 +3439,9 @@ impl eframe::App for App {
         
         self.render_settings(ctx);
         self.show_help_window(ctx);
+        self.render_lookup_window(ctx);
         self.render_rename_dialog(ctx);
+        self.render_duplicate_window(ctx);
         self.render_custom_model_dialog(ctx);
     }
     
@@ -2981,6 +3451,24 @@ impl eframe::App for App {
     }
 }
 
+// 重复条目处理方式的显示名称
+fn duplicate_policy_name(policy: DuplicatePolicy) -> &'static str {
+    match policy {
+        DuplicatePolicy::FirstWins => "保留第一个",
+        DuplicatePolicy::LastWins => "保留最后一个",
+        DuplicatePolicy::Error => "报错",
+    }
+}
+
+// 条目顺序的显示名称
+fn sort_order_name(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Key => "按上下文和原文",
+        SortOrder::Reference => "按源码位置",
+        SortOrder::Input => "保持输入顺序",
+    }
+}
+
 #[allow(dead_code)]
 fn format_system_time(time: SystemTime) -> String {
     match time.duration_since(UNIX_EPOCH) {